@@ -8,10 +8,28 @@ pub struct MyAssets
     pub player: Handle<Image>,
     #[asset(path = "rock.png")]
     pub rock: Handle<Image>,
+    #[asset(path = "tree.png")]
+    pub tree: Handle<Image>,
+    #[asset(path = "sapling.png")]
+    pub sapling: Handle<Image>,
     #[asset(path = "water.png")]
     pub water: Handle<Image>,
+    #[asset(path = "deep_water.png")]
+    pub deep_water: Handle<Image>,
     #[asset(path = "grass_var1.png")]
     pub grass: Handle<Image>,
+    #[asset(path = "grass_var2.png")]
+    pub grass_var2: Handle<Image>,
+    #[asset(path = "sand.png")]
+    pub sand: Handle<Image>,
+    #[asset(path = "forest_floor.png")]
+    pub forest_floor: Handle<Image>,
+    #[asset(path = "stone.png")]
+    pub stone: Handle<Image>,
+    #[asset(path = "dirt.png")]
+    pub dirt: Handle<Image>,
+    #[asset(path = "bridge.png")]
+    pub bridge: Handle<Image>,
     #[asset(path = "feesh_man_sheet.png")]
     pub merchant: Handle<Image>,
     #[asset(path = "cart.png")]
@@ -0,0 +1,53 @@
+use std::fs;
+
+/// File read for settings that are not given on the command line.
+/// Each line is `key = value`, lines starting with `#` are ignored.
+pub const CONFIG_FILE: &str = "game.cfg";
+
+/// Returns the value of the setting `key`.
+/// The command line (`--key value` or `--key=value`) takes precedence over the config file.
+/// # Example
+/// ```no_run
+/// // cargo run -- --seed 1234
+/// let seed = config::get("seed");
+/// ```
+pub fn get(key: &str) -> Option<String>
+{
+    from_args(key).or_else(|| from_file(key))
+}
+
+/// Returns the value of the setting `key` parsed as `T`, if present and valid.
+pub fn parse<T: std::str::FromStr>(key: &str) -> Option<T>
+{
+    get(key).and_then(|value| value.parse().ok())
+}
+
+fn from_args(key: &str) -> Option<String>
+{
+    let flag = format!("--{}", key);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next()
+    {
+        if arg == flag
+        {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&flag).and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+fn from_file(key: &str) -> Option<String>
+{
+    let contents = fs::read_to_string(CONFIG_FILE).ok()?;
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, value)| value.trim().to_string())
+}
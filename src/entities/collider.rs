@@ -1,25 +1,361 @@
-use bevy::{prelude::*, sprite::collide_aabb::collide};
+use std::ops::BitOr;
+
+use bevy::ecs::system::SystemParam;
+use bevy::utils::{HashMap, HashSet};
+use bevy::prelude::*;
+
+use crate::world::coords::WorldPos;
+use crate::world::resources::WorldData;
+use crate::GameState;
+
+use super::shape::{Contact, Shape};
+use super::unit::{Mass, Unit, Velocity};
+
+/// Side of a cell of the collider grid, in world units.
+/// About the size of the common colliders, so most of them fall in a cell or two.
+pub const GRID_CELL_SIZE: f32 = 4.0;
+/// Part of the overlap between two units resolved every frame.
+/// Below `1.0` units sink into each other a little and push apart softly.
+pub const SEPARATION_STIFFNESS: f32 = 0.5;
+
+/// Collision layers as bit flags. Colliders belong to layers, units are blocked by the layers in their mask.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Reflect)]
+pub struct Layers(pub u32);
+
+impl Layers {
+    pub const NONE: Layers = Layers(0);
+    /// Rocks, trees, carts and everything else nothing can go through.
+    pub const SOLID: Layers = Layers(1 << 0);
+    /// Water, blocks whatever can't swim.
+    pub const WATER: Layers = Layers(1 << 1);
+    pub const ALL: Layers = Layers(u32::MAX);
+
+    /// Returns true if the two sets of layers have a layer in common.
+    pub fn intersects(self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, other: Layers) -> Layers {
+        Layers(self.0 | other.0)
+    }
+}
 
 #[derive(Component)]
 pub struct Collider {
-    pub size: Vec2,
+    pub shape: Shape,
     pub active: bool,
+    pub layers: Layers,
+    /// Triggers never block anything, they send a [`TriggerEvent`] when a unit whose mask
+    /// shares a layer with them goes in or out.
+    pub trigger: bool,
 }
 
 impl Collider {
-    pub fn is_colliding(
+    /// A collider on the given layers that blocks units.
+    pub fn solid(shape: Shape, layers: Layers) -> Self {
+        Self {
+            shape,
+            active: true,
+            layers,
+            trigger: false,
+        }
+    }
+
+    /// A trigger that reports the units going through it.
+    pub fn trigger(shape: Shape, layers: Layers) -> Self {
+        Self {
+            shape,
+            active: true,
+            layers,
+            trigger: true,
+        }
+    }
+
+    /// Returns true if the collider stops units moving with the given mask.
+    pub fn blocks(&self, mask: Layers) -> bool {
+        self.active && !self.trigger && self.layers.intersects(mask)
+    }
+
+    /// Returns how the other shape overlaps the collider, whatever their layers.
+    /// The normal pushes the other shape out of the collider.
+    pub fn contact(&self, self_translate: Vec3, other: &Shape, other_translate: Vec3) -> Option<Contact> {
+        other.contact(other_translate.xz(), &self.shape, self_translate.xz())
+    }
+
+    /// Returns true if the other shape overlaps the collider, whatever their layers.
+    pub fn overlaps(&self, self_translate: Vec3, other: &Shape, other_translate: Vec3) -> bool {
+        self.contact(self_translate, other, other_translate).is_some()
+    }
+}
+
+/// Sent when a unit goes in or out of a trigger.
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TriggerEvent {
+    Enter { trigger: Entity, unit: Entity },
+    Exit { trigger: Entity, unit: Entity },
+}
+
+/// Pairs of trigger and unit currently overlapping.
+#[derive(Resource, Default)]
+pub struct TriggerContacts(HashSet<(Entity, Entity)>);
+
+/// Broadphase for the colliders: every collider is listed in the grid cells its bounding box overlaps,
+/// so movement only has to check the colliders in the cells it passes through.
+#[derive(Resource, Default)]
+pub struct ColliderGrid {
+    cells: HashMap<IVec2, Vec<Entity>>,
+    /// First and last cell covered by each collider.
+    entries: HashMap<Entity, (IVec2, IVec2)>,
+}
+
+impl ColliderGrid {
+    /// Returns the first and last cell covered by a box.
+    fn cell_range(center: Vec2, size: Vec2) -> (IVec2, IVec2) {
+        let cell = |position: Vec2| (position / GRID_CELL_SIZE).floor().as_ivec2();
+        (cell(center - size / 2.), cell(center + size / 2.))
+    }
+
+    /// Adds the collider to the cells covered by its box, moving it if it was already in the grid.
+    pub fn insert(&mut self, entity: Entity, center: Vec2, size: Vec2) {
+        let range = Self::cell_range(center, size);
+        if self.entries.get(&entity) == Some(&range) {
+            return;
+        }
+        self.remove(entity);
+        for x in range.0.x..=range.1.x {
+            for y in range.0.y..=range.1.y {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(entity);
+            }
+        }
+        self.entries.insert(entity, range);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        let Some((min, max)) = self.entries.remove(&entity) else {
+            return;
+        };
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let cell = IVec2::new(x, y);
+                if let Some(entities) = self.cells.get_mut(&cell) {
+                    entities.retain(|other| *other != entity);
+                    if entities.is_empty() {
+                        self.cells.remove(&cell);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns every collider listed in the cells covered by the box, each one once.
+    pub fn query(&self, center: Vec2, size: Vec2) -> HashSet<Entity> {
+        let (min, max) = Self::cell_range(center, size);
+        let mut found = HashSet::default();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                if let Some(entities) = self.cells.get(&IVec2::new(x, y)) {
+                    found.extend(entities.iter().copied());
+                }
+            }
+        }
+        found
+    }
+}
+
+/// The colliders units can bump into, looked up through the collider grid.
+#[derive(SystemParam)]
+pub struct Colliders<'w, 's> {
+    grid: Res<'w, ColliderGrid>,
+    colliders: Query<
+        'w,
+        's,
+        (Entity, &'static Transform, &'static Collider),
+        (Without<Unit>, Without<Camera>),
+    >,
+}
+
+impl<'w, 's> Colliders<'w, 's> {
+    /// Returns the colliders, triggers included, that may overlap the box centered on `translation`.
+    pub fn near(
         &self,
-        self_translate: Vec3,
-        other_translate: Vec3,
-        other_size: Vec2,
-    ) -> bool {
-        return self.active
-            && collide(
-                self_translate.xz().extend(0.),
-                self.size,
-                other_translate.xz().extend(0.),
-                other_size,
+        translation: Vec3,
+        size: Vec2,
+    ) -> impl Iterator<Item = (Entity, &Transform, &Collider)> {
+        self.grid
+            .query(translation.xz(), size)
+            .into_iter()
+            .filter_map(|entity| self.colliders.get(entity).ok())
+    }
+}
+
+/// Keeps the collider grid in sync as colliders spawn, move and despawn.
+pub fn update_collider_grid(
+    mut grid: ResMut<ColliderGrid>,
+    colliders: Query<
+        (Entity, &Transform, &Collider),
+        (Without<Unit>, Or<(Changed<Transform>, Changed<Collider>)>),
+    >,
+    mut removed: RemovedComponents<Collider>,
+) {
+    for entity in removed.read() {
+        grid.remove(entity);
+    }
+    for (entity, transform, collider) in &colliders {
+        grid.insert(entity, transform.translation.xz(), collider.shape.size());
+    }
+}
+
+/// Moves every unit by its velocity, sliding along the colliders in its way, then applies friction.
+pub fn move_units(
+    mut units: Query<(&mut Transform, &mut Velocity, &Unit)>,
+    colliders: Colliders,
+    world: Res<WorldData>,
+    time: Res<Time>,
+) {
+    let dtime = time.delta_seconds();
+    for (mut transform, mut velocity, unit) in &mut units {
+        let previous_translation = transform.translation;
+        unit.slide(&mut transform, velocity.motion(dtime), &colliders);
+        // never walk onto a chunk that isn't generated and spawned yet
+        let was_loaded = world.is_chunk_loaded(WorldPos::from(previous_translation).chunk());
+        if was_loaded && !world.is_chunk_loaded(WorldPos::from(transform.translation).chunk()) {
+            transform.translation = previous_translation;
+        }
+        velocity.apply_friction(dtime);
+    }
+}
+
+/// Pushes overlapping units apart, the lighter one moving the most.
+/// Units are never pushed into colliders.
+/// Hidden units, like the merchant out of opening hours, are not around to push or be pushed.
+pub fn separate_units(
+    mut units: Query<(Entity, &mut Transform, &Unit, &Mass, Option<&Visibility>)>,
+    colliders: Colliders,
+    world: Res<WorldData>,
+) {
+    let bodies = units
+        .iter()
+        .filter(|(.., visibility)| *visibility != Some(&Visibility::Hidden))
+        .map(|(entity, transform, unit, mass, _)| {
+            (entity, transform.translation.xz(), unit.size, 1. / mass.0.max(f32::EPSILON))
+        })
+        .collect::<Vec<_>>();
+
+    // bucket the units by grid cell, so only units close to each other are compared
+    let mut cells: HashMap<IVec2, Vec<usize>> = HashMap::default();
+    for (index, (_, center, size, _)) in bodies.iter().enumerate() {
+        let (min, max) = ColliderGrid::cell_range(*center, *size);
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                cells.entry(IVec2::new(x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    let mut pushes = vec![Vec2::ZERO; bodies.len()];
+    let mut checked = HashSet::default();
+    for indices in cells.values() {
+        for (i, a) in indices.iter().enumerate() {
+            for b in indices[i + 1..].iter() {
+                if !checked.insert((*a, *b)) {
+                    continue;
+                }
+                let (_, center_a, size_a, inverse_mass_a) = bodies[*a];
+                let (_, center_b, size_b, inverse_mass_b) = bodies[*b];
+                let total = inverse_mass_a + inverse_mass_b;
+                let delta = center_b - center_a;
+                let overlap = (size_a + size_b) / 2. - delta.abs();
+                if overlap.x <= 0. || overlap.y <= 0. || total <= 0. {
+                    continue;
+                }
+                // push apart along the axis that overlaps the least
+                let away = |value: f32| if value < 0. { -1. } else { 1. };
+                let push = if overlap.x < overlap.y {
+                    Vec2::new(overlap.x * away(delta.x), 0.)
+                } else {
+                    Vec2::new(0., overlap.y * away(delta.y))
+                } * SEPARATION_STIFFNESS;
+                pushes[*a] -= push * inverse_mass_a / total;
+                pushes[*b] += push * inverse_mass_b / total;
+            }
+        }
+    }
+
+    for ((entity, _, _, _), push) in bodies.iter().zip(pushes) {
+        if push == Vec2::ZERO {
+            continue;
+        }
+        let Ok((_, mut transform, unit, _, _)) = units.get_mut(*entity) else {
+            continue;
+        };
+        let mut pushed = *transform;
+        unit.slide(&mut pushed, Vec3::new(push.x, 0., push.y), &colliders);
+        // same as walking, never get pushed onto a chunk that isn't generated and spawned yet
+        let was_loaded = world.is_chunk_loaded(WorldPos::from(transform.translation).chunk());
+        if !was_loaded || world.is_chunk_loaded(WorldPos::from(pushed.translation).chunk()) {
+            *transform = pushed;
+        }
+    }
+}
+
+/// Sends a [`TriggerEvent`] for every unit that went in or out of a trigger since the last frame.
+pub fn detect_triggers(
+    units: Query<(Entity, &Transform, &Unit)>,
+    colliders: Colliders,
+    mut contacts: ResMut<TriggerContacts>,
+    mut events: EventWriter<TriggerEvent>,
+) {
+    let mut current = HashSet::default();
+    for (unit_entity, unit_transform, unit) in &units {
+        for (trigger, transform, collider) in colliders.near(unit_transform.translation, unit.size) {
+            if collider.trigger
+                && collider.active
+                && collider.layers.intersects(unit.mask)
+                && collider.overlaps(transform.translation, &unit.shape(), unit_transform.translation)
+            {
+                current.insert((trigger, unit_entity));
+            }
+        }
+    }
+
+    for (trigger, unit) in current.difference(&contacts.0) {
+        events.send(TriggerEvent::Enter {
+            trigger: *trigger,
+            unit: *unit,
+        });
+    }
+    for (trigger, unit) in contacts.0.difference(&current) {
+        events.send(TriggerEvent::Exit {
+            trigger: *trigger,
+            unit: *unit,
+        });
+    }
+    contacts.0 = current;
+}
+
+pub struct ColliderPlugin;
+
+impl Plugin for ColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColliderGrid>();
+        app.init_resource::<TriggerContacts>();
+        app.add_event::<TriggerEvent>();
+        // after every unit picked where it goes this frame, before the transforms are propagated.
+        // The grid is brought up to date first, so units bump into the colliders spawned or moved this frame.
+        app.add_systems(
+            PostUpdate,
+            (
+                update_collider_grid,
+                move_units.run_if(in_state(GameState::Ready)),
+                separate_units,
+                detect_triggers,
             )
-            .is_some();
+                .chain()
+                .before(bevy::transform::TransformSystem::TransformPropagate),
+        );
     }
 }
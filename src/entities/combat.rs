@@ -0,0 +1,229 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::GameState;
+
+use super::unit::{Health, Unit};
+
+/// Seconds a unit stays tinted after being hit.
+pub const FLASH_DURATION: f32 = 0.15;
+/// Tint of a unit that was just hit.
+pub const HIT_TINT: Color = Color::rgb(1.0, 0.3, 0.3);
+/// Real seconds the game slows down for when a blow lands, unless set with the `hit_stop` setting.
+pub const DEFAULT_HIT_STOP: f32 = 0.06;
+/// Speed of the game during a hit-stop.
+const HIT_STOP_SPEED: f32 = 0.05;
+
+/// Asks for `amount` damage to be dealt to `target`. Only [`apply_damage`] changes health,
+/// everything else reacts to the [`DeathEvent`]s it sends.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    /// Whatever dealt the damage, if anything did.
+    pub source: Option<Entity>,
+    pub amount: u32,
+}
+
+/// Sent once when a unit's health drops to zero.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    /// Whatever dealt the killing blow, if anything did.
+    pub killer: Option<Entity>,
+}
+
+/// Part of the damage a unit shrugs off, from `0.0` for none to `1.0` for all of it.
+#[derive(Component, Reflect, Default, Clone, Copy)]
+#[reflect(Component)]
+pub struct Resistance(pub f32);
+
+/// Sent when damage was actually dealt, after resistances and invulnerability.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct HitEvent {
+    pub entity: Entity,
+    pub amount: u32,
+}
+
+/// Units ignore all damage until the timer finishes.
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
+
+/// Seconds a unit is invulnerable for after taking damage.
+#[derive(Component, Reflect, Default, Clone, Copy)]
+#[reflect(Component)]
+pub struct InvulnerabilityWindow(pub f32);
+
+/// A unit tinted after a hit, wearing a copy of its material until the timer finishes.
+#[derive(Component)]
+pub struct Flash {
+    pub timer: Timer,
+    /// Material the unit wears again once the flash is over.
+    pub original: Handle<StandardMaterial>,
+}
+
+/// Slows the game down for a moment when a blow lands, so hits are felt.
+#[derive(Resource)]
+pub struct HitStop {
+    /// Real seconds the game is slowed down for, `0` to disable it.
+    pub duration: f32,
+    remaining: f32,
+}
+
+impl HitStop {
+    pub fn from_config() -> Self {
+        Self {
+            duration: crate::config::parse("hit_stop").unwrap_or(DEFAULT_HIT_STOP),
+            remaining: 0.,
+        }
+    }
+}
+
+/// Applies the damage of the frame, dead units take no more damage.
+/// Units with an [`InvulnerabilityWindow`] become [`Invulnerable`] after taking damage.
+pub fn apply_damage(
+    mut commands: Commands,
+    mut damages: EventReader<DamageEvent>,
+    mut targets: Query<(
+        &mut Health,
+        Option<&Resistance>,
+        Option<&Invulnerable>,
+        Option<&InvulnerabilityWindow>,
+    )>,
+    mut hits: EventWriter<HitEvent>,
+    mut deaths: EventWriter<DeathEvent>,
+) {
+    // the invulnerability only shows up next frame, keep track of it for this one
+    let mut invulnerable_now = HashSet::new();
+    for damage in damages.read() {
+        let Ok((mut health, resistance, invulnerable, window)) = targets.get_mut(damage.target)
+        else {
+            continue;
+        };
+        if health.current == 0 || invulnerable.is_some() || invulnerable_now.contains(&damage.target) {
+            continue;
+        }
+        let resisted = resistance.map_or(0., |resistance| resistance.0.clamp(0., 1.));
+        let amount = (damage.amount as f32 * (1. - resisted)).round() as u32;
+        if amount == 0 {
+            continue;
+        }
+        health.current = health.current.saturating_sub(amount);
+        hits.send(HitEvent {
+            entity: damage.target,
+            amount,
+        });
+        if let Some(window) = window.filter(|window| window.0 > 0.) {
+            commands
+                .entity(damage.target)
+                .insert(Invulnerable(Timer::from_seconds(window.0, TimerMode::Once)));
+            invulnerable_now.insert(damage.target);
+        }
+        if health.current == 0 {
+            deaths.send(DeathEvent {
+                entity: damage.target,
+                killer: damage.source,
+            });
+        }
+    }
+}
+
+/// Ends the invulnerability of units whose timer finished.
+pub fn update_invulnerability(
+    mut commands: Commands,
+    mut units: Query<(Entity, &mut Invulnerable)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable) in &mut units {
+        if invulnerable.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+/// Tints the units that were hit with a copy of their material.
+pub fn flash_on_hit(
+    mut commands: Commands,
+    mut hits: EventReader<HitEvent>,
+    mut units: Query<(&mut Handle<StandardMaterial>, Option<&mut Flash>), With<Unit>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for hit in hits.read() {
+        let Ok((mut material, flash)) = units.get_mut(hit.entity) else {
+            continue;
+        };
+        // already wearing its own copy, flash it again
+        if let Some(mut flash) = flash {
+            flash.timer.reset();
+            continue;
+        }
+        // sprites share their materials, tint a copy so only this unit flashes
+        let Some(mut tinted) = materials.get(material.id()).cloned() else {
+            continue;
+        };
+        tinted.base_color = HIT_TINT;
+        let original = std::mem::replace(&mut *material, materials.add(tinted));
+        commands.entity(hit.entity).insert(Flash {
+            timer: Timer::from_seconds(FLASH_DURATION, TimerMode::Once),
+            original,
+        });
+    }
+}
+
+/// Gives the units their own material back once the flash is over, dropping the tinted copy.
+pub fn update_flash(
+    mut commands: Commands,
+    mut units: Query<(Entity, &mut Flash, &mut Handle<StandardMaterial>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, mut material) in &mut units {
+        if !flash.timer.tick(time.delta()).finished() {
+            continue;
+        }
+        *material = flash.original.clone();
+        commands.entity(entity).remove::<Flash>();
+    }
+}
+
+/// Slows the game down when a blow lands, then brings it back to speed.
+/// Counts real time, the virtual clock being the one slowed down.
+pub fn hit_stop(
+    mut hits: EventReader<HitEvent>,
+    mut stop: ResMut<HitStop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+) {
+    if hits.read().count() > 0 && stop.duration > 0. {
+        stop.remaining = stop.duration;
+        virtual_time.set_relative_speed(HIT_STOP_SPEED);
+    } else if stop.remaining > 0. {
+        stop.remaining -= real_time.delta_seconds();
+        if stop.remaining <= 0. {
+            virtual_time.set_relative_speed(1.);
+        }
+    }
+}
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Resistance>();
+        app.register_type::<InvulnerabilityWindow>();
+        app.insert_resource(HitStop::from_config());
+        app.add_event::<DamageEvent>();
+        app.add_event::<HitEvent>();
+        app.add_event::<DeathEvent>();
+        // reactions to hits and deaths should run after apply_damage to see them the frame they happen
+        app.add_systems(
+            Update,
+            (
+                update_invulnerability,
+                apply_damage,
+                (flash_on_hit, hit_stop),
+                update_flash,
+            )
+                .chain()
+                .run_if(in_state(GameState::Ready)),
+        );
+    }
+}
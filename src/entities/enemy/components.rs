@@ -8,3 +8,7 @@ pub struct MeleeRange(pub f32);
 
 #[derive(Component)]
 pub struct Cooldown(pub Timer);
+
+/// Experience earned by whoever kills the enemy.
+#[derive(Component)]
+pub struct XpReward(pub u32);
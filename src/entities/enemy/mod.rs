@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::entities::combat::apply_damage;
 use crate::GameState;
 
 pub mod components;
@@ -8,6 +9,10 @@ mod systems;
 
 use self::resources::*;
 use self::systems::*;
+
+/// How many fish men come out together at night, for every one that shows up during the day.
+pub const NIGHT_FISH_MEN: usize = 2;
+
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin
@@ -20,7 +25,9 @@ impl Plugin for EnemyPlugin
                 (
                     update_enemy.run_if(in_state(GameState::Ready)),
                     deal_damage.run_if(in_state(GameState::Ready)),
+                    on_enemy_death.after(apply_damage).run_if(in_state(GameState::Ready)),
                     kill_enemies.run_if(in_state(GameState::Ready)),
+                    spawn_creatures.run_if(in_state(GameState::Ready)),
                 ),
             );
     }
@@ -5,11 +5,18 @@ use bevy_health_bar3d::prelude::*;
 use bevy_sprite3d::Sprite3d;
 use bevy_sprite3d::Sprite3dParams;
 
-use crate::entities::collider::Collider;
+use crate::entities::collider::{Collider, Layers};
+use crate::entities::combat::{DamageEvent, DeathEvent, Resistance};
 use crate::entities::player::components::Player;
+use crate::environment::resources::{Weather, WorldClock};
+use crate::world::biome::CreatureKind;
+use crate::world::coords::WorldPos;
+use crate::world::components::Creature;
+use crate::world::events::SpawnCreature;
 use crate::MyAssets;
 
 use super::components::*;
+use super::NIGHT_FISH_MEN;
 use super::resources::*;
 
 use crate::entities::unit::*;
@@ -18,16 +25,67 @@ pub fn spawn_enemy(
     mut commands: Commands,
     assets: Res<MyAssets>,
     mut sprite_params: Sprite3dParams,
+) {
+    spawn_fish_man(&mut commands, &assets, &mut sprite_params, Vec3::new(20., 1., 20.), None);
+}
+
+/// Spawns the creatures requested by the world as chunks get loaded.
+/// Fish men come out in groups at night.
+pub fn spawn_creatures(
+    mut commands: Commands,
+    assets: Res<MyAssets>,
+    mut sprite_params: Sprite3dParams,
+    mut events: EventReader<SpawnCreature>,
+    clock: Res<WorldClock>,
+) {
+    for event in events.read() {
+        match event.kind {
+            CreatureKind::FishMan => {
+                if event.health.is_some() {
+                    spawn_fish_man(
+                        &mut commands,
+                        &assets,
+                        &mut sprite_params,
+                        event.translation,
+                        event.health,
+                    );
+                    continue;
+                }
+                let count = if clock.is_night() { NIGHT_FISH_MEN } else { 1 };
+                for i in 0..count {
+                    let translation = event.translation + Vec3::new(i as f32, 0., 0.);
+                    spawn_fish_man(&mut commands, &assets, &mut sprite_params, translation, None)
+                }
+            }
+        }
+    }
+}
+
+/// Spawns a fish man at the given position, with the given health or a fresh one's.
+fn spawn_fish_man(
+    commands: &mut Commands,
+    assets: &MyAssets,
+    sprite_params: &mut Sprite3dParams,
+    translation: Vec3,
+    health: Option<u32>,
 ) {
     commands.spawn((
         Enemy,
+        Creature(CreatureKind::FishMan),
         Health {
-            current: 100,
+            current: health.unwrap_or(100),
             max: 125,
         },
         Speed(3.),
+        Mass(1.0),
+        Velocity::default(),
+        // scales turn some of the blows
+        Resistance(0.2),
+        XpReward(100),
         Unit {
             size: Vec2::new(0.5, 0.5),
+            // fish men swim
+            mask: Layers::SOLID,
         },
         MeleeRange(2.0),
         Damage(10),
@@ -36,10 +94,10 @@ pub fn spawn_enemy(
             image: assets.fish_man.clone(),
             pixels_per_metre: 16.0,
             unlit: true,
-            transform: Transform::from_xyz(20., 1., 20.),
+            transform: Transform::from_translation(translation),
             ..Default::default()
         }
-        .bundle(&mut sprite_params),
+        .bundle(sprite_params),
         BarBundle::<Health> {
             width: BarWidth::new(1.),
             offset: BarOffset::new(1.),
@@ -49,27 +107,36 @@ pub fn spawn_enemy(
 }
 
 pub fn update_enemy(
-    mut commands: Commands,
-    mut enemies: Query<(Entity, &mut Transform, &Enemy, &Speed, &Unit, &Health)>,
-    colliders: Query<(&Transform, &Collider), (Without<Unit>, Without<Camera>)>,
-    players: Query<(&mut Transform, &Player), (Without<Enemy>, Without<Camera>, Without<Collider>)>,
-    time: Res<Time>,
+    mut enemies: Query<(&Transform, &Enemy, &Speed, &mut Velocity), With<Health>>,
+    players: Query<(&Transform, &Player), (Without<Enemy>, Without<Camera>, Without<Collider>)>,
+    weather: Res<Weather>,
 ) {
-    let dtime = time.delta_seconds();
     let player = players.single();
-    for (entity, mut transform, _, speed, unit, health) in &mut enemies {
-        if health.current == 0 {
-            commands.entity(entity).remove::<Health>();
-            // commands.entity(entity).despawn();
-            continue;
-        }
-
+    for (transform, _, speed, mut velocity) in &mut enemies {
+        velocity.walk = Vec3::ZERO;
         let direction = player.0.translation - transform.translation;
         if direction.length() <= 1.0 {
             continue;
         }
         let direction = direction.normalize_or_zero();
-        unit.move_and_slide(&mut transform, direction, speed, &colliders, dtime);
+        // storms make fish men restless
+        let aggression = weather.at(WorldPos::from(transform.translation).chunk()).aggression();
+        velocity.walk = direction * speed.0 * aggression;
+    }
+}
+
+/// Dead enemies stop moving and lose their health bar, they are despawned by `kill_enemies`.
+pub fn on_enemy_death(
+    mut commands: Commands,
+    mut deaths: EventReader<DeathEvent>,
+    mut enemies: Query<&mut Velocity, With<Enemy>>,
+) {
+    for death in deaths.read() {
+        if let Ok(mut velocity) = enemies.get_mut(death.entity) {
+            velocity.walk = Vec3::ZERO;
+            commands.entity(death.entity).remove::<Health>();
+            // commands.entity(entity).despawn();
+        }
     }
 }
 
@@ -80,23 +147,26 @@ pub fn kill_enemies(    mut commands: Commands,mut enemies: Query<(Entity, &Enem
 }
 
 pub fn deal_damage(
-    mut enemies: Query<(&mut Transform, &Enemy, &MeleeRange, &Damage, &mut Cooldown)>,
-    mut players: Query<(&mut Transform, &Player, &mut Health), Without<Enemy>>,
+    mut enemies: Query<(Entity, &Transform, &MeleeRange, &Damage, &mut Cooldown), (With<Enemy>, With<Health>)>,
+    players: Query<(Entity, &Transform), (With<Player>, Without<Enemy>)>,
+    mut damage_events: EventWriter<DamageEvent>,
     time: Res<Time>,
+    weather: Res<Weather>,
 ) {
-    let mut player = players.single_mut();
-    for (transform, _, range, damage, mut cooldown) in &mut enemies {
-        if player.0.translation.distance(transform.translation) <= range.0 {
-            cooldown.0.tick(time.delta());
+    let (player, player_transform) = players.single();
+    for (enemy, transform, range, damage, mut cooldown) in &mut enemies {
+        if player_transform.translation.distance(transform.translation) <= range.0 {
+            // enemies attack faster in storms
+            let aggression = weather.at(WorldPos::from(transform.translation).chunk()).aggression();
+            cooldown.0.tick(time.delta().mul_f32(aggression));
             if !cooldown.0.just_finished() {
                 continue;
             }
-            if player.2.current > damage.0 {
-                player.2.current -= damage.0;
-            } else {
-                player.2.current = 0;
-            }
-            info!("Player hit")
+            damage_events.send(DamageEvent {
+                target: player,
+                source: Some(enemy),
+                amount: damage.0,
+            });
         }
     }
 }
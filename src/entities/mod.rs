@@ -1,5 +1,7 @@
 pub mod collider;
+pub mod combat;
 pub mod enemy;
 pub mod player;
+pub mod shape;
 pub mod shop;
 pub mod unit;
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use crate::world::biome::WaterBody;
+
 #[derive(Component)]
 pub struct Player;
 
@@ -9,6 +11,10 @@ pub struct Slash(pub Timer);
 #[derive(Component)]
 pub struct FishingFloat(pub Timer);
 
+/// Kind of water a fishing float landed in, decides which fish bite.
+#[derive(Component)]
+pub struct FishingSpot(pub WaterBody);
+
 #[derive(Component, Deref, DerefMut)]
 pub struct AnimationTimer(pub Timer);
 
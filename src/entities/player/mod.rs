@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use crate::entities::collider::{move_units, separate_units};
+use crate::entities::combat::apply_damage;
 use crate::GameState;
 
 pub mod components;
@@ -10,6 +12,14 @@ use self::resources::*;
 use self::systems::*;
 
 pub const CAMERA_OFFSET: Vec3 = Vec3::new(0., 10., 25.);
+/// Chance of a fish biting every time the float bobs, during the day.
+pub const BITE_CHANCE: f64 = 0.1;
+/// Speed a fishing float drifts downstream at on a river, in units per second.
+pub const FLOAT_DRIFT_SPEED: f32 = 0.4;
+/// Impulse given to enemies hit by the player, a fish man flies back about one unit.
+pub const KNOCKBACK_IMPULSE: f32 = 8.0;
+/// Seconds the player can't be hurt again after a hit, unless set with the `invulnerability` setting.
+pub const PLAYER_INVULNERABILITY: f32 = 0.8;
 
 pub struct PlayerPlugin;
 
@@ -22,9 +32,20 @@ impl Plugin for PlayerPlugin
         });
         app.add_systems(OnEnter(GameState::Spawning), spawn_player);
         app.add_systems(Update, update_player.run_if(in_state(GameState::Ready)));
+        app.add_systems(
+            PostUpdate,
+            follow_player
+                .after(move_units)
+                .after(separate_units)
+                .before(bevy::transform::TransformSystem::TransformPropagate)
+                .run_if(in_state(GameState::Ready)),
+        );
         app.add_systems(Update, update_slash.run_if(in_state(GameState::Ready)));
         app.add_systems(Update, update_fishing_float.run_if(in_state(GameState::Ready)));
         app.add_systems(Update, catch_fish.run_if(in_state(GameState::Ready)));
+        app.add_systems(Update, edit_terrain.run_if(in_state(GameState::Ready)));
+        app.add_systems(Update, on_player_hit.after(apply_damage).run_if(in_state(GameState::Ready)));
+        app.add_systems(Update, gain_xp.after(apply_damage).run_if(in_state(GameState::Ready)));
         app.add_systems(Update, level_up.run_if(in_state(GameState::Ready)));
         app.add_systems(
             Update,
@@ -3,21 +3,22 @@ use bevy_health_bar3d::configuration::{BarBundle, BarOffset, BarWidth};
 use bevy_sprite3d::AtlasSprite3d;
 use bevy_sprite3d::AtlasSprite3dComponent;
 use bevy_sprite3d::Sprite3dParams;
-use rand::RngCore;
+use rand::Rng;
 
 use crate::assets::MyAssets;
-use crate::entities::collider::Collider;
-use crate::entities::enemy::components::Enemy;
+use crate::entities::collider::{Collider, Layers};
+use crate::entities::combat::{DamageEvent, DeathEvent, HitEvent, InvulnerabilityWindow};
+use crate::entities::enemy::components::{Enemy, XpReward};
 use crate::entities::unit::*;
-use crate::world::CHUNK_RADIUS;
-use crate::world::components::TileType;
+use crate::environment::resources::{Weather, WorldClock};
+use crate::world::biome;
+use crate::world::coords::{WorldPos, TILE_SIZE};
+use crate::world::events::{EditTerrain, TerrainEdit};
 use crate::world::resources::WorldData;
-use crate::world::systems::get_chunk_pos;
-use crate::world::CHUNK_SIDE;
 
 use super::components::*;
 use super::resources::*;
-use super::CAMERA_OFFSET;
+use super::{BITE_CHANCE, CAMERA_OFFSET, FLOAT_DRIFT_SPEED, KNOCKBACK_IMPULSE, PLAYER_INVULNERABILITY};
 
 pub fn spawn_player(
     mut commands: Commands,
@@ -35,9 +36,15 @@ pub fn spawn_player(
         StateTimer(Timer::from_seconds(0.3, TimerMode::Once)),
         Xp(0),
         Speed(3.5),
+        Mass(2.0),
+        Velocity::default(),
+        InvulnerabilityWindow(
+            crate::config::parse("invulnerability").unwrap_or(PLAYER_INVULNERABILITY),
+        ),
         Damage(10),
         Unit {
             size: Vec2::new(0.5, 0.5),
+            mask: Layers::SOLID | Layers::WATER,
         },
         AtlasSprite3d {
             atlas: assets.player_moving.clone(),
@@ -59,23 +66,22 @@ pub fn spawn_player(
 pub fn update_player(
     mut players: Query<
         (
+            Entity,
             &mut Transform,
             &Player,
             &Speed,
-            &Unit,
+            &mut Velocity,
             &mut AnimationState,
             &mut StateTimer,
             &Damage,
         ),
         (Without<Camera>, Without<Enemy>),
     >,
-    mut camera: Query<(&Camera, &mut Transform)>,
-    mut settings: ResMut<PlayerSettings>,
-    colliders: Query<(&Transform, &Collider), (Without<Unit>, Without<Camera>)>,
-    mut enemies: Query<
-        (&Enemy, &mut Health, &mut Transform),
-        (Without<Player>, Without<Collider>, Without<Camera>),
+    enemies: Query<
+        (Entity, &Transform, &mut Velocity, &Mass),
+        (With<Enemy>, Without<Player>, Without<Collider>, Without<Camera>),
     >,
+    damage_events: EventWriter<DamageEvent>,
     input: Res<Input<KeyCode>>,
     time: Res<Time>,
     mut commands: Commands,
@@ -83,8 +89,8 @@ pub fn update_player(
     assets: Res<MyAssets>,
     mut sprite_params: Sprite3dParams,
 ) {
-    let dtime = time.delta_seconds();
-    let (mut transform, _, speed, unit, mut state, mut state_timer, damage) = players.single_mut();
+    let (entity, mut transform, _, speed, mut velocity, mut state, mut state_timer, damage) =
+        players.single_mut();
 
     let direction = get_direction_vector(&input, &mut transform);
 
@@ -99,6 +105,7 @@ pub fn update_player(
             Slash(Timer::from_seconds(0.3, TimerMode::Once)),
             Unit {
                 size: Vec2::new(0.5, 0.5),
+                mask: Layers::NONE,
             },
             AtlasSprite3d {
                 atlas: assets.slash.clone(),
@@ -115,7 +122,7 @@ pub fn update_player(
         ));
         // do attacking stuff
 
-        attack_enemy(enemies, &transform, damage);
+        attack_enemy(enemies, damage_events, entity, &transform, damage);
     }
 
     if input.pressed(KeyCode::F) {
@@ -134,41 +141,28 @@ pub fn update_player(
 
             let fishing_translation =
                 transform.translation + transform.left() * state_timer.0.elapsed_secs() * 5.;
-            let chunk_pos = get_chunk_pos(fishing_translation.xz());
-
-            match world.chunks.get(&chunk_pos) {
-                Some(chunk) => {
-                    let x_inside = fishing_translation.x / 2. - (chunk_pos.0 * CHUNK_SIDE - CHUNK_RADIUS) as f32 + 0.5;
-                    let z_inside = fishing_translation.z / 2. - (chunk_pos.1 * CHUNK_SIDE - CHUNK_RADIUS) as f32 + 0.5;
-
-                    let mut i = (x_inside).floor() as usize;
-                    let mut j = (z_inside).floor() as usize;
-                    if j > 5 {j = 4;}
-                    if i > 5 {i = 4;}
-                    if let TileType::Water = chunk.tiles[i][j]
-                    {
-                        *state = AnimationState::Fishing;
-                        commands.spawn((
-                            FishingFloat(Timer::from_seconds(0.3, TimerMode::Once)),
-                            AtlasSprite3d {
-                                atlas: assets.float.clone(),
-                                pixels_per_metre: 16.0,
-                                index: 0 as usize,
-                                unlit: true,
-                                transform: Transform {
-                                    translation: Vec3 {
-                                        y: 0.5,
-                                        ..fishing_translation
-                                    },
-                                    ..*transform
+            match world.water_body_at(WorldPos::from(fishing_translation)) {
+                Some(water) => {
+                    *state = AnimationState::Fishing;
+                    commands.spawn((
+                        FishingFloat(Timer::from_seconds(0.3, TimerMode::Once)),
+                        FishingSpot(water),
+                        AtlasSprite3d {
+                            atlas: assets.float.clone(),
+                            pixels_per_metre: 16.0,
+                            index: 0 as usize,
+                            unlit: true,
+                            transform: Transform {
+                                translation: Vec3 {
+                                    y: 0.5,
+                                    ..fishing_translation
                                 },
-                                ..default()
-                            }
-                            .bundle(&mut sprite_params),
-                        ));
-                    } else {
-                        *state = AnimationState::Idle;
-                    }
+                                ..*transform
+                            },
+                            ..default()
+                        }
+                        .bundle(&mut sprite_params),
+                    ));
                 }
                 None => *state = AnimationState::Idle,
             }
@@ -179,26 +173,58 @@ pub fn update_player(
 
 
     let direction = direction.normalize_or_zero();
+    velocity.walk = direction * speed.0;
+}
 
-    unit.move_and_slide(&mut transform, direction, speed, &colliders, dtime);
-
-    // move camera on top of player
-    if settings.camera_locked {
-        for (_, mut camera_transform) in &mut camera {
-            camera_transform.translation = transform.translation + CAMERA_OFFSET;
-        }
+/// Keeps the camera on top of the player once they moved this frame.
+pub fn follow_player(
+    players: Query<&Transform, (With<Player>, Without<Camera>)>,
+    mut camera: Query<(&Camera, &mut Transform)>,
+    settings: Res<PlayerSettings>,
+) {
+    if !settings.camera_locked {
+        return;
+    }
+    let transform = players.single();
+    for (_, mut camera_transform) in &mut camera {
+        camera_transform.translation = transform.translation + CAMERA_OFFSET;
     }
 }
 
+/// Digs (G), fills (T) or bridges (B) the tile in front of the player.
+pub fn edit_terrain(
+    players: Query<&Transform, With<Player>>,
+    input: Res<Input<KeyCode>>,
+    mut edit_events: EventWriter<EditTerrain>,
+) {
+    let edit = if input.just_pressed(KeyCode::G) {
+        TerrainEdit::Dig
+    } else if input.just_pressed(KeyCode::T) {
+        TerrainEdit::Fill
+    } else if input.just_pressed(KeyCode::B) {
+        TerrainEdit::Bridge
+    } else {
+        return;
+    };
+    let transform = players.single();
+    let target = transform.translation + transform.left() * TILE_SIZE;
+    edit_events.send(EditTerrain {
+        tile: WorldPos::from(target).tile(),
+        edit,
+    });
+}
+
 fn attack_enemy(
     mut enemies: Query<
-        (&Enemy, &mut Health, &mut Transform),
-        (Without<Player>, Without<Collider>, Without<Camera>),
+        (Entity, &Transform, &mut Velocity, &Mass),
+        (With<Enemy>, Without<Player>, Without<Collider>, Without<Camera>),
     >,
+    mut damage_events: EventWriter<DamageEvent>,
+    player: Entity,
     transform: &Mut<Transform>,
     damage: &Damage,
 ) {
-    for (_, mut enemy_health, mut enemy_transform) in enemies.iter_mut() {
+    for (enemy, enemy_transform, mut velocity, mass) in enemies.iter_mut() {
         let player_transform = &(**transform);
 
         let player_direction = player_transform.rotation.mul_vec3(Vec3::new(-1., 0., 0.));
@@ -211,15 +237,13 @@ fn attack_enemy(
         info!("Angle: {}", angle);
 
         if angle > 0.5 && distance_to_enemy.length() < 3.0 {
-            let player_damage = damage.0;
             info!("Damage!");
-            if enemy_health.current < player_damage {
-                info!("Enemy dead!");
-                enemy_health.current = 0;
-            } else {
-                enemy_health.current -= player_damage;
-            }
-            knockback_enemy(&mut enemy_transform, player_transform);
+            damage_events.send(DamageEvent {
+                target: enemy,
+                source: Some(player),
+                amount: damage.0,
+            });
+            knockback_enemy(&mut velocity, mass, enemy_transform, player_transform);
         }
     }
 }
@@ -293,6 +317,32 @@ pub fn update_player_sprite(
     };
 }
 
+/// Logs the damage the player takes.
+pub fn on_player_hit(mut hits: EventReader<HitEvent>, players: Query<&Health, With<Player>>) {
+    for hit in hits.read() {
+        if let Ok(health) = players.get(hit.entity) {
+            info!("Player hit for {}, {}/{} left", hit.amount, health.current, health.max);
+        }
+    }
+}
+
+/// Rewards the player with the experience of the enemies they kill.
+pub fn gain_xp(
+    mut deaths: EventReader<DeathEvent>,
+    rewards: Query<&XpReward>,
+    mut players: Query<&mut Xp, With<Player>>,
+) {
+    for death in deaths.read() {
+        let Some(killer) = death.killer else {
+            continue;
+        };
+        if let (Ok(reward), Ok(mut xp)) = (rewards.get(death.entity), players.get_mut(killer)) {
+            info!("Enemy dead! +{} xp", reward.0);
+            xp.0 += reward.0;
+        }
+    }
+}
+
 pub fn level_up(
     // operate on anything that has Xp and Health
     mut query: Query<(&mut Xp, &mut Health, &mut Speed, &mut Damage)>,
@@ -308,10 +358,15 @@ pub fn level_up(
     }
 }
 
-fn knockback_enemy(enemy_transform: &mut Transform, player_transform: &Transform) {
+fn knockback_enemy(
+    velocity: &mut Velocity,
+    mass: &Mass,
+    enemy_transform: &Transform,
+    player_transform: &Transform,
+) {
     let direction = enemy_transform.translation - player_transform.translation;
-    let direction = direction.normalize_or_zero();
-    enemy_transform.translation += direction
+    let direction = Vec3::new(direction.x, 0., direction.z).normalize_or_zero();
+    velocity.apply_impulse(direction * KNOCKBACK_IMPULSE, mass);
 }
 
 pub fn update_slash(
@@ -342,18 +397,21 @@ pub fn catch_fish(
         ),
         Without<Camera>,
     >,
-    mut floats: Query<(&FishingFloat, &mut AtlasSprite3dComponent), Without<Player>>,
+    mut floats: Query<(&FishingFloat, &FishingSpot, &mut AtlasSprite3dComponent), Without<Player>>,
     input: Res<Input<KeyCode>>,
 ) {
     let (_, mut state, mut xp) = players.single_mut();
-    if let Ok((_, mut atlas)) = floats.get_single_mut() {
+    if let Ok((_, spot, mut atlas)) = floats.get_single_mut() {
         if input.pressed(KeyCode::Space) {
             if matches!(*state, AnimationState::Fishing) {
 
                 if atlas.index == 2 || atlas.index == 3 {
                     *state = AnimationState::Idle;
-                    xp.0 += 500;
-                    info!("Caught FISH!!!!!!");
+                    // every kind of water has its own fish
+                    if let Some(fish) = biome::roll(spot.0.fish(), &mut rand::thread_rng()) {
+                        xp.0 += fish.xp();
+                        info!("Caught a {:?}!!!!!!", fish);
+                    }
                 }                 
                 atlas.index = 4;
     
@@ -366,11 +424,28 @@ pub fn catch_fish(
 
 pub fn update_fishing_float(
     mut commands: Commands,
-    mut floats: Query<(Entity, &mut FishingFloat, &mut AtlasSprite3dComponent)>,
+    mut floats: Query<(Entity, &mut FishingFloat, &mut AtlasSprite3dComponent, &mut Transform)>,
     time: Res<Time>,
+    clock: Res<WorldClock>,
+    weather: Res<Weather>,
+    world: Res<WorldData>,
 ) {
     let mut rng = rand::thread_rng();
-    for (entity, mut float, mut atlas) in &mut floats {
+    for (entity, mut float, mut atlas, mut transform) in &mut floats {
+        // floats drift downstream, as long as there is water to drift on
+        if let Some(flow) = world.flow_at(WorldPos::from(transform.translation)) {
+            let drift = Vec3::new(flow.x as f32, 0., flow.y as f32).normalize_or_zero()
+                * FLOAT_DRIFT_SPEED
+                * time.delta_seconds();
+            if world.water_body_at(WorldPos::from(transform.translation + drift)).is_some() {
+                transform.translation += drift;
+            }
+        }
+
+        // fish bite more at dawn and dusk, and in the rain
+        let weather = weather.at(WorldPos::from(transform.translation).chunk());
+        let bite_chance =
+            (BITE_CHANCE * clock.time_of_day().fish_activity() * weather.fish_activity()).min(1.0);
         float.0.tick(time.delta());
         if float.0.finished() {
             if atlas.index == 4 {
@@ -378,7 +453,7 @@ pub fn update_fishing_float(
             } else {
                 float.0.reset();
                 if atlas.index == 1 {
-                    if rng.next_u32() % 10 == 0 {
+                    if rng.gen_bool(bite_chance) {
                         atlas.index = 2;
                     } else {
                         atlas.index = 0;
@@ -0,0 +1,371 @@
+use bevy::prelude::*;
+
+/// Outline of a collider or unit on the ground, centered on its translation.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Shape {
+    /// Axis aligned box of the given size.
+    Box(Vec2),
+    /// Circle of the given radius.
+    Circle(f32),
+    /// Every point closer than `radius` to the segment going from `-half_segment` to `half_segment`.
+    Capsule { half_segment: Vec2, radius: f32 },
+}
+
+/// How two overlapping shapes touch.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Contact {
+    /// Direction the first shape has to move in to get out of the second one.
+    pub normal: Vec2,
+    /// How far the first shape has to move along the normal.
+    pub depth: f32,
+}
+
+impl Contact {
+    fn flip(self) -> Self {
+        Self {
+            normal: -self.normal,
+            depth: self.depth,
+        }
+    }
+}
+
+/// A segment, as its two ends.
+type Segment = (Vec2, Vec2);
+
+impl Shape {
+    /// A capsule going from `-length / 2` to `length / 2` along x, ends included.
+    pub fn capsule_x(length: f32, radius: f32) -> Self {
+        Shape::Capsule {
+            half_segment: Vec2::new((length / 2. - radius).max(0.), 0.),
+            radius,
+        }
+    }
+
+    /// Size of the smallest box holding the shape.
+    pub fn size(&self) -> Vec2 {
+        match *self {
+            Shape::Box(size) => size,
+            Shape::Circle(radius) => Vec2::splat(2. * radius),
+            Shape::Capsule {
+                half_segment,
+                radius,
+            } => 2. * (half_segment.abs() + radius),
+        }
+    }
+
+    /// Returns how the shape centered on `position` overlaps the other shape, if they overlap.
+    pub fn contact(&self, position: Vec2, other: &Shape, other_position: Vec2) -> Option<Contact> {
+        match (self.round(position), other.round(other_position)) {
+            (None, None) => box_box(position, self.size(), other_position, other.size()),
+            (Some((segment, radius)), Some((other_segment, other_radius))) => {
+                round_round(segment, radius, other_segment, other_radius)
+            }
+            (Some((segment, radius)), None) => {
+                box_round(other_position, other.size(), segment, radius)
+            }
+            (None, Some((other_segment, other_radius))) => {
+                box_round(position, self.size(), other_segment, other_radius).map(Contact::flip)
+            }
+        }
+    }
+
+    /// Returns the segment and radius of circles and capsules centered on `position`.
+    fn round(&self, position: Vec2) -> Option<(Segment, f32)> {
+        match *self {
+            Shape::Box(_) => None,
+            Shape::Circle(radius) => Some(((position, position), radius)),
+            Shape::Capsule {
+                half_segment,
+                radius,
+            } => Some(((position - half_segment, position + half_segment), radius)),
+        }
+    }
+}
+
+/// Returns the sign of `value`, counting zero as positive so shapes on top of each other still get pushed apart.
+fn side(value: f32) -> f32 {
+    if value < 0. {
+        -1.
+    } else {
+        1.
+    }
+}
+
+fn box_box(position: Vec2, size: Vec2, other_position: Vec2, other_size: Vec2) -> Option<Contact> {
+    let delta = position - other_position;
+    let overlap = (size + other_size) / 2. - delta.abs();
+    if overlap.x <= 0. || overlap.y <= 0. {
+        return None;
+    }
+    // out along the axis that overlaps the least
+    Some(if overlap.x < overlap.y {
+        Contact {
+            normal: Vec2::new(side(delta.x), 0.),
+            depth: overlap.x,
+        }
+    } else {
+        Contact {
+            normal: Vec2::new(0., side(delta.y)),
+            depth: overlap.y,
+        }
+    })
+}
+
+fn round_round(segment: Segment, radius: f32, other: Segment, other_radius: f32) -> Option<Contact> {
+    let (point, other_point) = closest_points(segment, other);
+    let delta = point - other_point;
+    let distance = delta.length();
+    let depth = radius + other_radius - distance;
+    if depth <= 0. {
+        return None;
+    }
+    // the segments cross, push out from the middle of the other one
+    let normal = if distance > f32::EPSILON {
+        delta / distance
+    } else {
+        ((segment.0 + segment.1) / 2. - (other.0 + other.1) / 2.)
+            .try_normalize()
+            .unwrap_or(Vec2::Y)
+    };
+    Some(Contact { normal, depth })
+}
+
+/// Contact of a circle or capsule with a box, the normal pushing the round shape out of the box.
+fn box_round(center: Vec2, size: Vec2, segment: Segment, radius: f32) -> Option<Contact> {
+    let (min, max) = (center - size / 2., center + size / 2.);
+    if !segment_hits_box(segment, min, max) {
+        let (point, box_point) = closest_to_box(segment, min, max);
+        let delta = point - box_point;
+        let distance = delta.length();
+        if distance >= radius {
+            return None;
+        }
+        if distance > f32::EPSILON {
+            return Some(Contact {
+                normal: delta / distance,
+                depth: radius - distance,
+            });
+        }
+    }
+
+    // the segment touches the box, out along the separating axis that overlaps the least
+    let direction = segment.1 - segment.0;
+    let mut axes = vec![Vec2::X, Vec2::Y];
+    if let Some(axis) = direction.perp().try_normalize() {
+        axes.push(axis);
+    }
+    let half = size / 2.;
+    axes.into_iter()
+        .flat_map(|axis| {
+            let box_center = center.dot(axis);
+            let box_extent = half.x * axis.x.abs() + half.y * axis.y.abs();
+            let (start, end) = (segment.0.dot(axis), segment.1.dot(axis));
+            let round_min = start.min(end) - radius;
+            let round_max = start.max(end) + radius;
+            // out on either side of the box
+            [
+                Contact {
+                    normal: axis,
+                    depth: box_center + box_extent - round_min,
+                },
+                Contact {
+                    normal: -axis,
+                    depth: round_max - (box_center - box_extent),
+                },
+            ]
+        })
+        .min_by(|a, b| a.depth.total_cmp(&b.depth))
+}
+
+/// Returns the point of the segment closest to `point`.
+fn closest_on_segment(segment: Segment, point: Vec2) -> Vec2 {
+    let direction = segment.1 - segment.0;
+    let length_squared = direction.length_squared();
+    if length_squared <= f32::EPSILON {
+        return segment.0;
+    }
+    let t = ((point - segment.0).dot(direction) / length_squared).clamp(0., 1.);
+    segment.0 + t * direction
+}
+
+/// Returns the closest points of two segments, the first one on `a`.
+fn closest_points(a: Segment, b: Segment) -> (Vec2, Vec2) {
+    let (a_direction, b_direction) = (a.1 - a.0, b.1 - b.0);
+    let (d1, d2) = (b_direction.perp_dot(a.0 - b.0), b_direction.perp_dot(a.1 - b.0));
+    let (d3, d4) = (a_direction.perp_dot(b.0 - a.0), a_direction.perp_dot(b.1 - a.0));
+    if d1 * d2 < 0. && d3 * d4 < 0. {
+        let crossing = a.0 + a_direction * (d1 / (d1 - d2));
+        return (crossing, crossing);
+    }
+    // apart, so one of the ends is part of the closest pair
+    [
+        (a.0, closest_on_segment(b, a.0)),
+        (a.1, closest_on_segment(b, a.1)),
+        (closest_on_segment(a, b.0), b.0),
+        (closest_on_segment(a, b.1), b.1),
+    ]
+    .into_iter()
+    .min_by(|(p, q), (r, s)| p.distance_squared(*q).total_cmp(&r.distance_squared(*s)))
+    .unwrap()
+}
+
+/// Returns true if the segment goes through the box.
+fn segment_hits_box(segment: Segment, min: Vec2, max: Vec2) -> bool {
+    let direction = segment.1 - segment.0;
+    let (mut enter, mut exit) = (0f32, 1f32);
+    for axis in 0..2 {
+        if direction[axis].abs() <= f32::EPSILON {
+            if segment.0[axis] <= min[axis] || segment.0[axis] >= max[axis] {
+                return false;
+            }
+            continue;
+        }
+        let t0 = (min[axis] - segment.0[axis]) / direction[axis];
+        let t1 = (max[axis] - segment.0[axis]) / direction[axis];
+        enter = enter.max(t0.min(t1));
+        exit = exit.min(t0.max(t1));
+        if enter >= exit {
+            return false;
+        }
+    }
+    true
+}
+
+/// Returns the closest points of a segment and a box it doesn't go through, the first one on the segment.
+fn closest_to_box(segment: Segment, min: Vec2, max: Vec2) -> (Vec2, Vec2) {
+    let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+    // both shapes are convex, so the closest pair has an end of the segment or a corner of the box
+    [segment.0, segment.1]
+        .map(|end| (end, end.clamp(min, max)))
+        .into_iter()
+        .chain(corners.map(|corner| (closest_on_segment(segment, corner), corner)))
+        .min_by(|(p, q), (r, s)| p.distance_squared(*q).total_cmp(&r.distance_squared(*s)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAPSULE: Shape = Shape::Capsule {
+        half_segment: Vec2::new(1.5, 0.),
+        radius: 0.4,
+    };
+
+    fn assert_contact(contact: Option<Contact>, normal: Vec2, depth: f32) {
+        let contact = contact.expect("the shapes should overlap");
+        assert!(
+            contact.normal.abs_diff_eq(normal, 1e-4),
+            "normal {} instead of {}",
+            contact.normal,
+            normal
+        );
+        assert!(
+            (contact.depth - depth).abs() < 1e-4,
+            "depth {} instead of {}",
+            contact.depth,
+            depth
+        );
+    }
+
+    /// Both shapes get pushed out of each other by the same depth, in opposite directions.
+    fn assert_symmetric(a: Shape, a_position: Vec2, b: Shape, b_position: Vec2) {
+        let contact = a.contact(a_position, &b, b_position).unwrap();
+        assert_contact(b.contact(b_position, &a, a_position), -contact.normal, contact.depth);
+    }
+
+    #[test]
+    fn capsule_x_spans_its_length() {
+        assert_eq!(CAPSULE, Shape::capsule_x(3.8, 0.4));
+        assert!(Shape::capsule_x(3.8, 0.4).size().abs_diff_eq(Vec2::new(3.8, 0.8), 1e-6));
+    }
+
+    #[test]
+    fn box_box() {
+        let unit = Shape::Box(Vec2::ONE);
+        assert_contact(unit.contact(Vec2::new(0.8, 0.), &unit, Vec2::ZERO), Vec2::X, 0.2);
+        assert_contact(unit.contact(Vec2::new(0.1, -0.7), &unit, Vec2::ZERO), -Vec2::Y, 0.3);
+        assert_symmetric(unit, Vec2::new(0.8, 0.1), unit, Vec2::ZERO);
+        // touching
+        assert_eq!(unit.contact(Vec2::new(1., 0.5), &unit, Vec2::ZERO), None);
+        // separated
+        assert_eq!(unit.contact(Vec2::new(2., 0.), &unit, Vec2::ZERO), None);
+        // on top of each other
+        assert_contact(unit.contact(Vec2::ZERO, &unit, Vec2::ZERO), Vec2::Y, 1.);
+    }
+
+    #[test]
+    fn box_circle() {
+        let (unit, circle) = (Shape::Box(Vec2::ONE), Shape::Circle(1.));
+        assert_contact(unit.contact(Vec2::new(1.2, 0.), &circle, Vec2::ZERO), Vec2::X, 0.3);
+        assert_contact(circle.contact(Vec2::ZERO, &unit, Vec2::new(1.2, 0.)), -Vec2::X, 0.3);
+        // against the corner of the box
+        let diagonal = Vec2::ONE.normalize();
+        assert_contact(
+            unit.contact(Vec2::ONE, &circle, Vec2::ZERO),
+            diagonal,
+            1. - 0.5 * 2f32.sqrt(),
+        );
+        assert_symmetric(unit, Vec2::new(0.9, 0.4), circle, Vec2::ZERO);
+        // touching
+        assert_eq!(unit.contact(Vec2::new(1.5, 0.), &circle, Vec2::ZERO), None);
+        // separated
+        assert_eq!(unit.contact(Vec2::new(1.3, 1.3), &circle, Vec2::ZERO), None);
+        // on top of each other
+        assert_contact(circle.contact(Vec2::ZERO, &unit, Vec2::ZERO), Vec2::X, 1.5);
+    }
+
+    #[test]
+    fn box_capsule() {
+        let small = Shape::Box(Vec2::splat(0.5));
+        assert_contact(small.contact(Vec2::new(1., 0.5), &CAPSULE, Vec2::ZERO), Vec2::Y, 0.15);
+        assert_contact(small.contact(Vec2::new(2., 0.), &CAPSULE, Vec2::ZERO), Vec2::X, 0.15);
+        assert_contact(CAPSULE.contact(Vec2::ZERO, &small, Vec2::new(1., 0.5)), -Vec2::Y, 0.15);
+        assert_symmetric(small, Vec2::new(1.9, 0.3), CAPSULE, Vec2::ZERO);
+        // the segment of the capsule goes through the box
+        assert_contact(small.contact(Vec2::new(0.3, 0.1), &CAPSULE, Vec2::ZERO), Vec2::Y, 0.55);
+        assert_contact(small.contact(Vec2::new(0.3, -0.1), &CAPSULE, Vec2::ZERO), -Vec2::Y, 0.55);
+        // touching, with a radius that adds up exactly
+        let wide = Shape::capsule_x(4., 0.5);
+        assert_eq!(small.contact(Vec2::new(0., 0.75), &wide, Vec2::ZERO), None);
+        assert_eq!(small.contact(Vec2::new(2.25, 0.), &wide, Vec2::ZERO), None);
+        // separated
+        assert_eq!(small.contact(Vec2::new(0., 1.), &CAPSULE, Vec2::ZERO), None);
+        // on top of each other
+        assert_contact(small.contact(Vec2::ZERO, &CAPSULE, Vec2::ZERO), -Vec2::Y, 0.65);
+    }
+
+    #[test]
+    fn circle_capsule() {
+        let circle = Shape::Circle(0.5);
+        assert_contact(circle.contact(Vec2::new(0., 0.8), &CAPSULE, Vec2::ZERO), Vec2::Y, 0.1);
+        // past the end of the segment
+        assert_contact(circle.contact(Vec2::new(2.2, 0.), &CAPSULE, Vec2::ZERO), Vec2::X, 0.2);
+        assert_symmetric(circle, Vec2::new(1.8, -0.5), CAPSULE, Vec2::ZERO);
+        // touching
+        assert_eq!(circle.contact(Vec2::new(0., 0.9), &CAPSULE, Vec2::ZERO), None);
+        // separated
+        assert_eq!(circle.contact(Vec2::new(0., 1.), &CAPSULE, Vec2::ZERO), None);
+        // on top of each other
+        assert_contact(circle.contact(Vec2::ZERO, &CAPSULE, Vec2::ZERO), Vec2::Y, 0.9);
+    }
+
+    #[test]
+    fn capsule_capsule() {
+        let short = Shape::capsule_x(2., 0.2);
+        let upright = Shape::Capsule {
+            half_segment: Vec2::Y,
+            radius: 0.2,
+        };
+        assert_contact(short.contact(Vec2::new(0., 0.3), &short, Vec2::ZERO), Vec2::Y, 0.1);
+        assert_symmetric(short, Vec2::new(0.5, 0.3), short, Vec2::ZERO);
+        // the segments cross
+        assert_contact(short.contact(Vec2::new(0., 0.1), &upright, Vec2::ZERO), Vec2::Y, 0.4);
+        // touching
+        assert_eq!(short.contact(Vec2::new(0., 0.4), &short, Vec2::ZERO), None);
+        // separated
+        assert_eq!(short.contact(Vec2::new(2.5, 0.), &short, Vec2::ZERO), None);
+        // on top of each other
+        assert_contact(short.contact(Vec2::ZERO, &short, Vec2::ZERO), Vec2::Y, 0.4);
+    }
+}
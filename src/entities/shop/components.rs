@@ -5,3 +5,7 @@ pub struct Merchant;
 
 #[derive(Component)]
 pub struct Cart;
+
+/// Trigger around the merchant, the player is at the shop while inside it.
+#[derive(Component)]
+pub struct ShopZone;
@@ -9,6 +9,13 @@ mod systems;
 use self::resources::*;
 use self::systems::*;
 
+/// Hour the merchant opens shop.
+pub const OPENING_HOUR: f32 = 8.0;
+/// Hour the merchant packs up and leaves for the night.
+pub const CLOSING_HOUR: f32 = 19.0;
+/// Size of the area around the merchant and their cart where the player is at the shop.
+pub const SHOP_ZONE_SIZE: Vec2 = Vec2::new(7.0, 5.0);
+
 pub struct ShopPlugin;
 
 impl Plugin for ShopPlugin
@@ -17,5 +24,6 @@ impl Plugin for ShopPlugin
     {
         app.add_systems(OnEnter(GameState::Spawning), spawn_merchant);
         app.add_systems(Update, update_merchant.run_if(in_state(GameState::Ready)));
+        app.add_systems(Update, visit_shop.run_if(in_state(GameState::Ready)));
     }
 }
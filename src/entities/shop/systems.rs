@@ -5,19 +5,25 @@ use bevy_sprite3d::Sprite3dParams;
 use rand::Rng;
 
 use crate::assets::MyAssets;
-use crate::entities::collider::Collider;
+use crate::entities::collider::{Collider, Layers, TriggerEvent};
+use crate::entities::player::components::Player;
+use crate::entities::shape::Shape;
 use crate::entities::unit::*;
+use crate::environment::resources::WorldClock;
+use crate::world::resources::WorldSeed;
 
 use super::components::*;
 use super::resources::*;
+use super::{CLOSING_HOUR, OPENING_HOUR, SHOP_ZONE_SIZE};
 
 pub fn spawn_merchant(
     mut commands: Commands,
     assets: Res<MyAssets>,
     mut sprite_params: Sprite3dParams,
+    seed: Res<WorldSeed>,
 )
 {
-    let mut rng = rand::thread_rng();
+    let mut rng = seed.rng();
     let cx = rng.gen::<i32>() % 10 - 5;
     let cy = rng.gen::<i32>() % 10 - 5;
 
@@ -28,8 +34,12 @@ pub fn spawn_merchant(
             max: 125,
         },
         Speed(3.5),
+        // the merchant stands their ground
+        Mass(f32::INFINITY),
+        Velocity::default(),
         Unit {
             size: Vec2::new(0.5, 0.5),
+            mask: Layers::SOLID | Layers::WATER,
         },
         Sprite3d {
             // atlas sheets crash for some reason ??
@@ -47,12 +57,19 @@ pub fn spawn_merchant(
         },
     ));
 
+    commands.spawn((
+        ShopZone,
+        Collider::trigger(Shape::Box(SHOP_ZONE_SIZE), Layers::ALL),
+        TransformBundle::from_transform(Transform::from_translation(Vec3::new(
+            cx as f32 + 1.0,
+            0.,
+            cy as f32 - 1.0,
+        ))),
+    ));
+
     commands.spawn((
         Cart,
-        Collider {
-            size: Vec2::new(3.8, 0.8),
-            active: true,
-        },
+        Collider::solid(Shape::capsule_x(3.8, 0.4), Layers::SOLID),
         Sprite3d {
             image: assets.cart.clone(),
             pixels_per_metre: 16.0,
@@ -69,19 +86,59 @@ pub fn spawn_merchant(
 }
 
 pub fn update_merchant(
-    mut merchants: Query<(&mut Transform, &Merchant, &Speed, &Unit), Without<Camera>>,
-    colliders: Query<(&Transform, &Collider), (Without<Unit>, Without<Camera>)>,
-    time: Res<Time>,
+    mut merchants: Query<(&Merchant, &Speed, &mut Velocity, &mut Visibility)>,
+    clock: Res<WorldClock>,
 )
 {
-    let dtime = time.delta_seconds();
-    for (mut transform, _, speed, unit) in &mut merchants
+    let is_open = is_open(&clock);
+    for (_, speed, mut velocity, mut visibility) in &mut merchants
     {
+        // the merchant is only around during opening hours
+        *visibility = if is_open { Visibility::Inherited } else { Visibility::Hidden };
+
         let mut rng = rand::thread_rng();
         // let direction = Vec3::new(rng.gen::<i32>() as f32, 0., rng.gen::<i32>() as f32);
         // let direction = direction.normalize_or_zero();
 
         let direction = Vec3::ZERO;
-        unit.move_and_slide(&mut transform, direction, speed, &colliders, dtime);
+        velocity.walk = direction * speed.0;
     }
 }
+
+/// Greets the player when they walk up to the merchant.
+pub fn visit_shop(
+    mut triggers: EventReader<TriggerEvent>,
+    zones: Query<(), With<ShopZone>>,
+    players: Query<(), With<Player>>,
+    clock: Res<WorldClock>,
+)
+{
+    for event in triggers.read()
+    {
+        match *event
+        {
+            TriggerEvent::Enter { trigger, unit } if zones.contains(trigger) && players.contains(unit) =>
+            {
+                if is_open(&clock)
+                {
+                    info!("Welcome to the shop!");
+                }
+                else
+                {
+                    info!("The merchant is away, come back at {}:00", OPENING_HOUR);
+                }
+            }
+            TriggerEvent::Exit { trigger, unit } if zones.contains(trigger) && players.contains(unit) =>
+            {
+                info!("Left the shop");
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Returns true if the merchant is open at the current time of day.
+pub fn is_open(clock: &WorldClock) -> bool
+{
+    clock.is_between(OPENING_HOUR, CLOSING_HOUR)
+}
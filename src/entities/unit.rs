@@ -1,12 +1,24 @@
 use bevy::prelude::*;
 use bevy_health_bar3d::prelude::*;
 
-use super::collider::Collider;
+use super::collider::{Colliders, Layers};
+use super::shape::Shape;
+
+/// Times a move is pushed back out of the colliders it hits before giving up on it.
+const SLIDE_ITERATIONS: usize = 4;
+/// Gap left between a unit and the collider it slides along, so it is not stuck in it next frame.
+const SLIDE_SKIN: f32 = 0.001;
+/// Friction of units that don't set their own.
+pub const DEFAULT_FRICTION: f32 = 8.0;
+/// Pushes slower than this, in units per second, come to a stop.
+const MIN_PUSH_SPEED: f32 = 0.05;
 
 #[derive(Component)]
 pub struct Unit
 {
     pub size: Vec2,
+    /// Layers of the colliders that block the unit.
+    pub mask: Layers,
 }
 
 #[derive(Component)]
@@ -16,6 +28,79 @@ pub struct Damage(pub u32);
 #[reflect(Component)]
 pub struct Speed(pub f32);
 
+/// How hard a unit is to push around when it bumps into other units.
+/// Only units with a mass collide with each other, `f32::INFINITY` never gets pushed.
+#[derive(Reflect, Component, Clone, Copy)]
+#[reflect(Component)]
+pub struct Mass(pub f32);
+
+impl Default for Mass
+{
+    fn default() -> Self
+    {
+        Self(1.0)
+    }
+}
+
+/// How fast a unit moves, every unit with a velocity is moved by `move_units`.
+#[derive(Reflect, Component, Clone, Copy)]
+#[reflect(Component)]
+pub struct Velocity
+{
+    /// Velocity the unit walks at, set every frame by whatever controls the unit.
+    pub walk: Vec3,
+    /// Velocity given by impulses, worn down by friction.
+    pub push: Vec3,
+    /// Part of the push lost every second, higher stops the unit sooner.
+    /// A push travels `push / friction` before it stops.
+    pub friction: f32,
+}
+
+impl Default for Velocity
+{
+    fn default() -> Self
+    {
+        Self::with_friction(DEFAULT_FRICTION)
+    }
+}
+
+impl Velocity
+{
+    pub fn with_friction(friction: f32) -> Self
+    {
+        Self {
+            walk: Vec3::ZERO,
+            push: Vec3::ZERO,
+            friction,
+        }
+    }
+
+    /// Pushes the unit, heavier units are pushed less and `f32::INFINITY` not at all.
+    pub fn apply_impulse(&mut self, impulse: Vec3, mass: &Mass)
+    {
+        if mass.0 > 0.
+        {
+            self.push += impulse / mass.0;
+        }
+    }
+
+    /// Returns how far the unit moves in `dtime` seconds.
+    pub fn motion(&self, dtime: f32) -> Vec3
+    {
+        (self.walk + self.push) * dtime
+    }
+
+    /// Wears the push down by the friction over `dtime` seconds.
+    pub fn apply_friction(&mut self, dtime: f32)
+    {
+        self.push *= (-self.friction * dtime).exp();
+        if self.push.length() < MIN_PUSH_SPEED
+        {
+            self.push = Vec3::ZERO;
+        }
+    }
+}
+
 #[derive(Component, Reflect)]
 pub struct Health
 {
@@ -33,61 +118,49 @@ impl Percentage for Health
 
 impl Unit
 {
-    pub fn move_and_slide(
-        &self,
-        transform: &mut Transform,
-        direction: Vec3,
-        speed: &Speed,
-        colliders: &Query<(&Transform, &Collider), (Without<Unit>, Without<Camera>)>,
-        dtime: f32,
-    )
+    /// Outline the unit collides with.
+    pub fn shape(&self) -> Shape
     {
-        let speed = speed.0 * dtime;
-        // Try to move separatelly on x and z axis to allow sliding near walls.
-        let mut next_translation_x = transform.translation + (speed * direction);
-        next_translation_x.z = transform.translation.z;
-        let mut next_translation_z = transform.translation + (speed * direction);
-        next_translation_z.x = transform.translation.x;
-        let mut is_colliding = false;
-        let mut will_collide_x = false;
-        let mut will_collide_z = false;
-        for (collider_transform, collider) in colliders.iter()
+        Shape::Box(self.size)
+    }
+
+    /// Moves the unit by `motion`, pushing it back out of the colliders it runs into
+    /// so it slides along walls and around round obstacles.
+    pub fn slide(&self, transform: &mut Transform, motion: Vec3, colliders: &Colliders)
+    {
+        let shape = self.shape();
+        // only the colliders around the unit and where it is heading can be hit
+        let reach = self.size + 2. * motion.xz().abs();
+        let start = transform.translation;
+        // a unit stuck in a collider can always walk out of it
+        let blocking = colliders
+            .near(start, reach)
+            .filter(|(_, collider_transform, collider)| {
+                collider.blocks(self.mask)
+                    && !collider.overlaps(collider_transform.translation, &shape, start)
+            })
+            .collect::<Vec<_>>();
+
+        let mut next_translation = start + motion;
+        for _ in 0..SLIDE_ITERATIONS
         {
-            if collider.is_colliding(
-                collider_transform.translation,
-                next_translation_x,
-                self.size,
-            )
-            {
-                will_collide_x = true;
-            }
-            if collider.is_colliding(
-                collider_transform.translation,
-                next_translation_z,
-                self.size,
-            )
+            let mut is_colliding = false;
+            for (_, collider_transform, collider) in blocking.iter()
             {
-                will_collide_z = true;
+                if let Some(contact) =
+                    collider.contact(collider_transform.translation, &shape, next_translation)
+                {
+                    let push = contact.normal * (contact.depth + SLIDE_SKIN);
+                    next_translation += Vec3::new(push.x, 0., push.y);
+                    is_colliding = true;
+                }
             }
-            if collider.is_colliding(
-                collider_transform.translation,
-                transform.translation,
-                self.size,
-            )
+            if !is_colliding
             {
-                is_colliding = true;
+                transform.translation = next_translation;
+                return;
             }
         }
-        let mut next_translation = transform.translation;
-        if is_colliding || !will_collide_x
-        {
-            next_translation.x = next_translation_x.x;
-        }
-        if is_colliding || !will_collide_z
-        {
-            next_translation.z = next_translation_z.z;
-        }
-
-        transform.translation = next_translation;
+        // wedged between colliders, stay put
     }
 }
@@ -0,0 +1,8 @@
+use bevy::prelude::*;
+
+/// A drop of rain falling around the player, despawned when it hits the ground.
+#[derive(Component)]
+pub struct RainDrop
+{
+    pub velocity: Vec3,
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+
+use crate::GameState;
+
+pub mod components;
+pub mod resources;
+mod systems;
+
+use self::resources::*;
+use self::systems::*;
+
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin
+{
+    fn build(&self, app: &mut App)
+    {
+        app.register_type::<WorldClock>();
+        app.insert_resource(WorldClock::from_config());
+        app.init_resource::<Weather>();
+        app.add_systems(OnEnter(GameState::Spawning), setup_weather);
+        app.add_systems(
+            Update,
+            (advance_clock, update_weather, apply_daylight, apply_fog)
+                .chain()
+                .run_if(in_state(GameState::Ready)),
+        );
+        app.add_systems(
+            Update,
+            (spawn_rain, update_rain).run_if(in_state(GameState::Ready)),
+        );
+    }
+}
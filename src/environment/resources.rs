@@ -0,0 +1,280 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::Rng;
+
+use crate::world::coords::ChunkPos;
+
+/// Hour the day starts at when `start_hour` is not set.
+pub const DEFAULT_START_HOUR: f32 = 8.0;
+/// Real seconds a whole in-game day lasts when `day_length` is not set.
+pub const DEFAULT_DAY_LENGTH: f32 = 600.0;
+
+/// Parts of the day, each with its own light and its own habits for fish and fish men.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Reflect)]
+pub enum TimeOfDay
+{
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+/// In-game time, advanced every frame.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct WorldClock
+{
+    /// Real seconds a whole in-game day lasts.
+    pub day_length: f32,
+    /// Time since midnight, as a fraction of the day in `0.0..1.0`.
+    pub time: f32,
+    /// Days passed since the game started.
+    pub day: u32,
+    pub paused: bool,
+}
+
+impl Default for WorldClock
+{
+    fn default() -> Self
+    {
+        Self {
+            day_length: DEFAULT_DAY_LENGTH,
+            time: DEFAULT_START_HOUR / 24.0,
+            day: 0,
+            paused: false,
+        }
+    }
+}
+
+impl WorldClock
+{
+    /// Reads the day length and starting hour from the command line or config file.
+    pub fn from_config() -> Self
+    {
+        let default = Self::default();
+        Self {
+            day_length: crate::config::parse("day_length").unwrap_or(default.day_length),
+            time: crate::config::parse::<f32>("start_hour")
+                .map_or(default.time, |hour| (hour / 24.0).rem_euclid(1.0)),
+            ..default
+        }
+    }
+
+    /// Moves the clock forward by `seconds` of real time.
+    pub fn tick(&mut self, seconds: f32)
+    {
+        if self.paused || self.day_length <= 0.0
+        {
+            return;
+        }
+        self.time += seconds / self.day_length;
+        while self.time >= 1.0
+        {
+            self.time -= 1.0;
+            self.day += 1;
+        }
+    }
+
+    /// Returns the hour of the day, in `0.0..24.0`.
+    pub fn hour(&self) -> f32
+    {
+        self.time * 24.0
+    }
+
+    pub fn time_of_day(&self) -> TimeOfDay
+    {
+        match self.hour()
+        {
+            hour if (5.0..7.0).contains(&hour) => TimeOfDay::Dawn,
+            hour if (7.0..18.0).contains(&hour) => TimeOfDay::Day,
+            hour if (18.0..20.0).contains(&hour) => TimeOfDay::Dusk,
+            _ => TimeOfDay::Night,
+        }
+    }
+
+    /// Returns true if the hour is between `from` and `to`, which may wrap around midnight.
+    pub fn is_between(&self, from: f32, to: f32) -> bool
+    {
+        let hour = self.hour();
+        if from <= to
+        {
+            (from..to).contains(&hour)
+        }
+        else
+        {
+            hour >= from || hour < to
+        }
+    }
+
+    pub fn is_night(&self) -> bool
+    {
+        self.time_of_day() == TimeOfDay::Night
+    }
+
+    /// Returns how bright it is, from `0.0` at night to `1.0` during the day.
+    /// The light fades in at dawn and out at dusk.
+    pub fn daylight(&self) -> f32
+    {
+        let hour = self.hour();
+        let ramp = |from: f32, to: f32| {
+            let t = ((hour - from) / (to - from)).clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        };
+        ramp(5.0, 7.0) - ramp(18.0, 20.0)
+    }
+}
+
+impl TimeOfDay
+{
+    /// How much more often fish bite than during the day, they feed at dawn and dusk.
+    pub fn fish_activity(&self) -> f64
+    {
+        match self
+        {
+            TimeOfDay::Dawn | TimeOfDay::Dusk => 2.0,
+            TimeOfDay::Day => 1.0,
+            TimeOfDay::Night => 0.5,
+        }
+    }
+}
+
+/// Side of a weather region, in chunks. Every region has its own weather.
+pub const WEATHER_REGION_SIZE: i32 = 6;
+/// Shortest and longest time a weather lasts, in real seconds.
+pub const WEATHER_DURATION: (f32, f32) = (60.0, 180.0);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Reflect)]
+pub enum WeatherKind
+{
+    Clear,
+    Rain,
+    Storm,
+    Fog,
+}
+
+/// Weather of a single region, it changes when `timer` finishes.
+#[derive(Clone, Debug)]
+pub struct RegionWeather
+{
+    pub kind: WeatherKind,
+    pub timer: Timer,
+}
+
+/// Weather of the regions around the player, plus what the player currently sees of it.
+/// `rain` and `fog` ease towards the weather of the player's region, so changes and region borders fade in.
+#[derive(Resource, Default)]
+pub struct Weather
+{
+    pub regions: HashMap<IVec2, RegionWeather>,
+    /// How hard it rains around the player, from `0.0` to `1.0`.
+    pub rain: f32,
+    /// How thick the fog around the player is, from `0.0` to `1.0`.
+    pub fog: f32,
+    /// Brightness of the last lightning strike, fades back to `0.0`.
+    pub flash: f32,
+}
+
+/// Mesh and material shared by every rain drop.
+#[derive(Resource)]
+pub struct WeatherAssets
+{
+    pub rain_mesh: Handle<Mesh>,
+    pub rain_material: Handle<StandardMaterial>,
+}
+
+impl Weather
+{
+    /// Returns the weather region containing the chunk.
+    pub fn region(chunk_pos: ChunkPos) -> IVec2
+    {
+        IVec2::new(
+            chunk_pos.x.div_euclid(WEATHER_REGION_SIZE),
+            chunk_pos.y.div_euclid(WEATHER_REGION_SIZE),
+        )
+    }
+
+    /// Returns the weather at the chunk, regions far from the player are always clear.
+    pub fn at(&self, chunk_pos: ChunkPos) -> WeatherKind
+    {
+        self.regions
+            .get(&Self::region(chunk_pos))
+            .map_or(WeatherKind::Clear, |region| region.kind)
+    }
+}
+
+impl RegionWeather
+{
+    pub fn new(kind: WeatherKind, rng: &mut impl Rng) -> Self
+    {
+        Self {
+            kind,
+            timer: Timer::from_seconds(
+                rng.gen_range(WEATHER_DURATION.0..WEATHER_DURATION.1),
+                TimerMode::Once,
+            ),
+        }
+    }
+}
+
+impl WeatherKind
+{
+    /// Picks the weather that follows this one.
+    pub fn next(&self, rng: &mut impl Rng) -> Self
+    {
+        let roll = rng.gen::<f32>();
+        match self
+        {
+            WeatherKind::Clear if roll < 0.3 => WeatherKind::Rain,
+            WeatherKind::Clear if roll < 0.5 => WeatherKind::Fog,
+            WeatherKind::Rain if roll < 0.25 => WeatherKind::Storm,
+            WeatherKind::Rain if roll < 0.7 => WeatherKind::Clear,
+            WeatherKind::Storm if roll < 0.7 => WeatherKind::Rain,
+            WeatherKind::Fog if roll < 0.6 => WeatherKind::Clear,
+            kind => *kind,
+        }
+    }
+
+    /// How hard it rains, from `0.0` to `1.0`.
+    pub fn rain(&self) -> f32
+    {
+        match self
+        {
+            WeatherKind::Rain => 0.5,
+            WeatherKind::Storm => 1.0,
+            WeatherKind::Clear | WeatherKind::Fog => 0.0,
+        }
+    }
+
+    /// How thick the fog is, from `0.0` to `1.0`.
+    pub fn fog(&self) -> f32
+    {
+        match self
+        {
+            WeatherKind::Fog => 1.0,
+            WeatherKind::Rain => 0.2,
+            WeatherKind::Storm => 0.35,
+            WeatherKind::Clear => 0.0,
+        }
+    }
+
+    /// How much more often fish bite than in clear weather, rain brings them up.
+    pub fn fish_activity(&self) -> f64
+    {
+        match self
+        {
+            WeatherKind::Rain => 1.8,
+            WeatherKind::Storm => 1.3,
+            WeatherKind::Clear | WeatherKind::Fog => 1.0,
+        }
+    }
+
+    /// How much faster enemies move and attack than in clear weather, storms make them restless.
+    pub fn aggression(&self) -> f32
+    {
+        match self
+        {
+            WeatherKind::Storm => 1.5,
+            _ => 1.0,
+        }
+    }
+}
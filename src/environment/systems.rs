@@ -0,0 +1,202 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::view::ColorGrading;
+use rand::Rng;
+
+use crate::entities::player::components::Player;
+use crate::world::coords::WorldPos;
+
+use super::components::*;
+use super::resources::*;
+
+/// Color of the sky at noon.
+const DAY_SKY: Color = Color::rgb(0.3, 0.3, 1.0);
+/// Color of the sky at midnight.
+const NIGHT_SKY: Color = Color::rgb(0.02, 0.02, 0.12);
+/// Exposure of the camera at midnight, every sprite is unlit so this is what darkens the scene.
+const NIGHT_EXPOSURE: f32 = -1.6;
+/// Saturation of the picture at midnight, colors fade in the dark.
+const NIGHT_SATURATION: f32 = 0.45;
+/// Exposure lost under the heaviest rain clouds.
+const RAIN_EXPOSURE: f32 = -0.6;
+/// Exposure added by a lightning strike.
+const LIGHTNING_EXPOSURE: f32 = 2.5;
+/// Average lightning strikes per second during a storm.
+const LIGHTNING_RATE: f64 = 0.15;
+/// How fast a lightning flash fades, per second.
+const FLASH_FADE: f32 = 3.0;
+/// How fast the rain and fog around the player follow the weather, per second.
+const WEATHER_EASE: f32 = 0.3;
+/// Regions further than this many regions from the player's region are forgotten.
+const WEATHER_RANGE: i32 = 1;
+
+/// Rain drops falling at once during the heaviest storm.
+const MAX_RAIN_DROPS: usize = 400;
+/// Rain drops spawned per frame at most, so rain starts gradually.
+const RAIN_DROPS_PER_FRAME: usize = 20;
+/// Half the side of the area rain falls in around the player.
+const RAIN_AREA: f32 = 22.0;
+const RAIN_HEIGHT: f32 = 14.0;
+const RAIN_SPEED: f32 = 18.0;
+
+const FOG_COLOR: Color = Color::rgb(0.62, 0.66, 0.72);
+/// Fog start and end distances from the camera in clear weather, far past anything on screen.
+const CLEAR_FOG: (f32, f32) = (200.0, 400.0);
+/// Fog start and end distances from the camera in the thickest fog.
+/// The player stands about 27 units from the camera and stays visible.
+const THICK_FOG: (f32, f32) = (22.0, 45.0);
+
+pub fn advance_clock(mut clock: ResMut<WorldClock>, time: Res<Time>)
+{
+    clock.tick(time.delta_seconds());
+}
+
+/// Darkens and desaturates the picture and the sky as night falls.
+/// Rain clouds darken it some more, lightning lights it up for an instant.
+pub fn apply_daylight(
+    clock: Res<WorldClock>,
+    weather: Res<Weather>,
+    mut cameras: Query<(&mut ColorGrading, &mut Camera3d)>,
+)
+{
+    let daylight = clock.daylight();
+    let sky = Vec4::from(NIGHT_SKY).lerp(Vec4::from(DAY_SKY), daylight);
+    for (mut color_grading, mut camera) in &mut cameras
+    {
+        color_grading.exposure = NIGHT_EXPOSURE * (1.0 - daylight)
+            + RAIN_EXPOSURE * weather.rain
+            + LIGHTNING_EXPOSURE * weather.flash;
+        color_grading.post_saturation = NIGHT_SATURATION + (1.0 - NIGHT_SATURATION) * daylight;
+        camera.clear_color = ClearColorConfig::Custom(Color::from(sky));
+    }
+}
+
+pub fn setup_weather(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+)
+{
+    commands.insert_resource(WeatherAssets {
+        rain_mesh: meshes.add(Mesh::from(shape::Box::new(0.03, 0.5, 0.03))),
+        rain_material: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.7, 0.78, 1.0, 0.5),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+/// Changes the weather of every region around the player when its time is up,
+/// then eases what the player sees towards the weather of their region.
+pub fn update_weather(
+    mut weather: ResMut<Weather>,
+    players: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+)
+{
+    let mut rng = rand::thread_rng();
+    let chunk_pos = WorldPos::from(players.single().translation).chunk();
+    let center = Weather::region(chunk_pos);
+
+    weather
+        .regions
+        .retain(|region, _| (*region - center).abs().max_element() <= WEATHER_RANGE);
+    for x in -WEATHER_RANGE..=WEATHER_RANGE
+    {
+        for y in -WEATHER_RANGE..=WEATHER_RANGE
+        {
+            let region = weather
+                .regions
+                .entry(center + IVec2::new(x, y))
+                .or_insert_with(|| RegionWeather::new(WeatherKind::Clear.next(&mut rng), &mut rng));
+            region.timer.tick(time.delta());
+            if region.timer.finished()
+            {
+                *region = RegionWeather::new(region.kind.next(&mut rng), &mut rng);
+            }
+        }
+    }
+
+    let kind = weather.at(chunk_pos);
+    let ease = (WEATHER_EASE * time.delta_seconds()).min(1.0);
+    weather.rain += (kind.rain() - weather.rain) * ease;
+    weather.fog += (kind.fog() - weather.fog) * ease;
+
+    weather.flash = (weather.flash - FLASH_FADE * time.delta_seconds()).max(0.0);
+    if kind == WeatherKind::Storm && rng.gen_bool((LIGHTNING_RATE * time.delta_seconds() as f64).min(1.0))
+    {
+        weather.flash = 1.0;
+    }
+}
+
+/// Thickens the fog around the camera as the weather gets foggy.
+pub fn apply_fog(weather: Res<Weather>, clock: Res<WorldClock>, mut cameras: Query<&mut FogSettings>)
+{
+    let start = CLEAR_FOG.0 + (THICK_FOG.0 - CLEAR_FOG.0) * weather.fog;
+    let end = CLEAR_FOG.1 + (THICK_FOG.1 - CLEAR_FOG.1) * weather.fog;
+    // the fog is as dark as the sky
+    let color = Vec4::from(NIGHT_SKY).lerp(Vec4::from(FOG_COLOR), clock.daylight());
+    for mut fog in &mut cameras
+    {
+        fog.color = Color::from(color);
+        fog.falloff = FogFalloff::Linear { start, end };
+    }
+}
+
+/// Spawns rain drops above the area around the player, as many as the rain calls for.
+pub fn spawn_rain(
+    mut commands: Commands,
+    weather: Res<Weather>,
+    weather_assets: Res<WeatherAssets>,
+    players: Query<&Transform, With<Player>>,
+    drops: Query<(), With<RainDrop>>,
+)
+{
+    let wanted = (weather.rain * MAX_RAIN_DROPS as f32) as usize;
+    let missing = wanted.saturating_sub(drops.iter().count()).min(RAIN_DROPS_PER_FRAME);
+    if missing == 0
+    {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    let center = players.single().translation;
+    // storms blow the rain sideways
+    let velocity = Vec3::new(-6.0 * weather.rain, -RAIN_SPEED, 0.0);
+    for _ in 0..missing
+    {
+        let translation = Vec3::new(
+            center.x + rng.gen_range(-RAIN_AREA..RAIN_AREA),
+            rng.gen_range(0.0..RAIN_HEIGHT),
+            center.z + rng.gen_range(-RAIN_AREA..RAIN_AREA),
+        );
+        commands.spawn((
+            RainDrop { velocity },
+            PbrBundle {
+                mesh: weather_assets.rain_mesh.clone(),
+                material: weather_assets.rain_material.clone(),
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Moves the rain drops and removes those that hit the ground.
+pub fn update_rain(
+    mut commands: Commands,
+    mut drops: Query<(Entity, &mut Transform, &RainDrop)>,
+    time: Res<Time>,
+)
+{
+    for (entity, mut transform, drop) in &mut drops
+    {
+        transform.translation += drop.velocity * time.delta_seconds();
+        if transform.translation.y < 0.0
+        {
+            commands.entity(entity).despawn();
+        }
+    }
+}
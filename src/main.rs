@@ -9,15 +9,20 @@ use bevy_prototype_lyon::prelude::*;
 use bevy_sprite3d::*;
 
 mod assets;
+mod config;
 mod entities;
+mod environment;
 mod ui;
 mod world;
 
 use assets::MyAssets;
+use entities::collider::ColliderPlugin;
+use entities::combat::CombatPlugin;
 use entities::enemy::EnemyPlugin;
 use entities::player::PlayerPlugin;
 use entities::shop::ShopPlugin;
-use entities::unit::{Health, Speed};
+use entities::unit::{Health, Mass, Speed, Velocity};
+use environment::EnvironmentPlugin;
 use ui::UIPlugin;
 use world::WorldPlugin;
 
@@ -32,9 +37,22 @@ enum GameState
 
 fn main()
 {
+    // exporting a map needs neither a window nor a GPU
+    if world::export::is_requested()
+    {
+        if let Err(error) = world::export::run()
+        {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
         .insert_resource(Msaa::Off)
         .register_type::<Speed>()
+        .register_type::<Mass>()
+        .register_type::<Velocity>()
         .register_type::<Health>()
         .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
         .add_plugins(HealthBarPlugin::<Health>::default())
@@ -58,10 +76,13 @@ fn main()
         .add_systems(OnEnter(GameState::Ready), game_setup)
         // systems that rely on the player being spawned should: run_if(in_state(GameState::Ready))
         .add_plugins(WorldInspectorPlugin::new())
+        .add_plugins(ColliderPlugin)
+        .add_plugins(CombatPlugin)
         .add_plugins(PlayerPlugin)
         // handle spawning and updating game components
         .add_plugins(EnemyPlugin)
         .add_plugins(WorldPlugin)
+        .add_plugins(EnvironmentPlugin)
         .add_plugins(ShopPlugin)
         //.add_plugins(BunPlugin)
         .add_plugins(UIPlugin)
@@ -78,21 +99,31 @@ fn finish_spawning(mut game_state: ResMut<NextState<GameState>>, input: Res<Inpu
 
 fn game_setup(mut commands: Commands)
 {
-    commands.spawn(Camera3dBundle {
-        camera: Camera {
-            hdr: true,
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                hdr: true,
+                ..default()
+            },
+            camera_3d: Camera3d {
+                clear_color: ClearColorConfig::Custom(Color::rgb(0.3, 0.3, 1.0)),
+                ..default()
+            },
+            projection: bevy::prelude::Projection::Perspective(PerspectiveProjection {
+                fov: std::f32::consts::PI / 6.0,
+                ..default()
+            }),
+            transform: Transform::from_translation(entities::player::CAMERA_OFFSET)
+                .with_rotation(Quat::from_rotation_x(-0.4)),
             ..default()
         },
-        camera_3d: Camera3d {
-            clear_color: ClearColorConfig::Custom(Color::rgb(0.3, 0.3, 1.0)),
+        // starts out clear, the weather thickens it
+        FogSettings {
+            falloff: FogFalloff::Linear {
+                start: 200.0,
+                end: 400.0,
+            },
             ..default()
         },
-        projection: bevy::prelude::Projection::Perspective(PerspectiveProjection {
-            fov: std::f32::consts::PI / 6.0,
-            ..default()
-        }),
-        transform: Transform::from_translation(entities::player::CAMERA_OFFSET)
-            .with_rotation(Quat::from_rotation_x(-0.4)),
-        ..default()
-    });
+    ));
 }
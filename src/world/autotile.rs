@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension};
+use bevy::render::texture::TextureFormatPixelInfo;
+
+use super::components::TileType;
+use super::coords::TilePos;
+
+// Bits of a shore mask, set when the neighbour in that direction is land.
+// North is towards -z, which is the top of the tile textures.
+pub const NORTH: u8 = 1 << 0;
+pub const EAST: u8 = 1 << 1;
+pub const SOUTH: u8 = 1 << 2;
+pub const WEST: u8 = 1 << 3;
+pub const NORTH_EAST: u8 = 1 << 4;
+pub const SOUTH_EAST: u8 = 1 << 5;
+pub const SOUTH_WEST: u8 = 1 << 6;
+pub const NORTH_WEST: u8 = 1 << 7;
+
+/// Width of the sand strip drawn along a shore, in pixels.
+const SHORE_WIDTH: f32 = 5.0;
+/// Width of the foam line between the sand and the water, in pixels.
+const FOAM_WIDTH: f32 = 1.5;
+const FOAM_COLOR: [u8; 4] = [164, 221, 219, 255];
+
+/// Neighbour offsets of every shore mask bit, in tile coordinates.
+const NEIGHBOURS: [(u8, i32, i32); 8] = [
+    (NORTH, 0, -1),
+    (EAST, 1, 0),
+    (SOUTH, 0, 1),
+    (WEST, -1, 0),
+    (NORTH_EAST, 1, -1),
+    (SOUTH_EAST, 1, 1),
+    (SOUTH_WEST, -1, 1),
+    (NORTH_WEST, -1, -1),
+];
+
+/// Returns which neighbours of a water tile are land, as a normalized shore mask.
+/// Land tiles, and neighbours `tile_at` doesn't know about, get no shore.
+pub fn shore_mask(
+    tile_type: TileType,
+    tile: TilePos,
+    tile_at: impl Fn(TilePos) -> Option<TileType>,
+) -> u8
+{
+    if !tile_type.is_water()
+    {
+        return 0;
+    }
+    let mask = NEIGHBOURS
+        .iter()
+        .filter(|(_, dx, dy)| tile_at(tile.offset(*dx, *dy)).is_some_and(|other| other.is_land()))
+        .fold(0, |mask, (bit, _, _)| mask | bit);
+    normalize(mask)
+}
+
+/// Drops the corner bits already covered by one of their edges,
+/// so the 256 possible masks come down to 47 different looking tiles.
+pub fn normalize(mask: u8) -> u8
+{
+    let mut mask = mask;
+    for (corner, first, second) in [
+        (NORTH_EAST, NORTH, EAST),
+        (SOUTH_EAST, SOUTH, EAST),
+        (SOUTH_WEST, SOUTH, WEST),
+        (NORTH_WEST, NORTH, WEST),
+    ]
+    {
+        if mask & (first | second) != 0
+        {
+            mask &= !corner;
+        }
+    }
+    mask
+}
+
+/// Every normalized mask that has a shore.
+pub fn shore_masks() -> impl Iterator<Item = u8>
+{
+    (1..=u8::MAX).filter(|mask| normalize(*mask) == *mask)
+}
+
+/// Draws the water texture with a sandy shore along the sides and corners of `mask`.
+/// Returns `None` if the textures aren't 8 bit RGBA images of the same size.
+pub fn shore_image(water: &Image, sand: &Image, mask: u8) -> Option<Image>
+{
+    let size = water.texture_descriptor.size;
+    if size != sand.texture_descriptor.size
+        || water.texture_descriptor.format.pixel_size() != 4
+        || sand.texture_descriptor.format.pixel_size() != 4
+    {
+        return None;
+    }
+    let (width, height) = (size.width as f32, size.height as f32);
+    let mut data = water.data.clone();
+
+    for y in 0..size.height
+    {
+        for x in 0..size.width
+        {
+            // distance from the pixel center to the closest bit of land
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let corner = |cx: f32, cy: f32| ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+            let distance = [
+                (NORTH, py),
+                (EAST, width - px),
+                (SOUTH, height - py),
+                (WEST, px),
+                (NORTH_EAST, corner(width, 0.)),
+                (SOUTH_EAST, corner(width, height)),
+                (SOUTH_WEST, corner(0., height)),
+                (NORTH_WEST, corner(0., 0.)),
+            ]
+            .iter()
+            .filter(|(bit, _)| mask & bit != 0)
+            .map(|(_, distance)| *distance)
+            .fold(f32::MAX, f32::min);
+
+            let index = ((y * size.width + x) * 4) as usize;
+            if distance < SHORE_WIDTH
+            {
+                data[index..index + 4].copy_from_slice(&sand.data[index..index + 4]);
+            }
+            else if distance < SHORE_WIDTH + FOAM_WIDTH
+            {
+                data[index..index + 4].copy_from_slice(&FOAM_COLOR);
+            }
+        }
+    }
+
+    Some(Image::new(
+        Extent3d {
+            depth_or_array_layers: 1,
+            ..size
+        },
+        TextureDimension::D2,
+        data,
+        water.texture_descriptor.format,
+    ))
+}
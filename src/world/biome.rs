@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+
+use super::components::TileType;
+
+/// Elevation under which the sea gets deep.
+pub const DEEP_WATER_LEVEL: f64 = -0.3;
+/// Elevation under which land is covered by sand.
+pub const BEACH_LEVEL: f64 = 0.06;
+/// Elevation above which land turns to rock.
+pub const HIGHLANDS_LEVEL: f64 = 0.55;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+pub enum Biome
+{
+    Ocean,
+    Beach,
+    Plains,
+    Forest,
+    Desert,
+    Highlands,
+    River,
+    Lake,
+}
+
+/// Kinds of water, each with its own fish.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum WaterBody
+{
+    River,
+    Lake,
+    Ocean,
+}
+
+/// Fish that can be caught.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FishKind
+{
+    Trout,
+    Salmon,
+    Carp,
+    Perch,
+    Pike,
+    Cod,
+    Tuna,
+    Pufferfish,
+}
+
+/// Things that can grow or lie on a tile.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum PropKind
+{
+    Rock,
+    Tree,
+    Sapling,
+}
+
+/// Creatures that can be encountered in the wild.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum CreatureKind
+{
+    FishMan,
+}
+
+/// An entry of a spawn table, `chance` is the probability of spawning on any given tile.
+#[derive(Clone, Copy, Debug)]
+pub struct SpawnEntry<T>
+{
+    pub kind: T,
+    pub chance: f64,
+}
+
+impl Biome
+{
+    /// Picks the biome from the elevation, temperature and moisture of a tile.
+    /// All three values are roughly in the `-1.0..1.0` range.
+    pub fn from_climate(elevation: f64, temperature: f64, moisture: f64) -> Self
+    {
+        if elevation < 0.0
+        {
+            Biome::Ocean
+        }
+        else if elevation < BEACH_LEVEL
+        {
+            Biome::Beach
+        }
+        else if elevation > HIGHLANDS_LEVEL
+        {
+            Biome::Highlands
+        }
+        else if temperature > 0.3 && moisture < -0.2
+        {
+            Biome::Desert
+        }
+        else if moisture > 0.15
+        {
+            Biome::Forest
+        }
+        else
+        {
+            Biome::Plains
+        }
+    }
+
+    /// Returns the ground tile of the biome at the given elevation.
+    pub fn tile(&self, elevation: f64) -> TileType
+    {
+        match self
+        {
+            Biome::Ocean if elevation < DEEP_WATER_LEVEL => TileType::DeepWater,
+            Biome::Ocean | Biome::River | Biome::Lake => TileType::ShallowWater,
+            Biome::Beach | Biome::Desert => TileType::Sand,
+            Biome::Plains => TileType::Grass,
+            Biome::Forest => TileType::ForestFloor,
+            Biome::Highlands => TileType::Rock,
+        }
+    }
+
+    /// Props that can be placed in the biome, at most one is placed per tile.
+    pub fn props(&self) -> &'static [SpawnEntry<PropKind>]
+    {
+        match self
+        {
+            Biome::Ocean | Biome::River | Biome::Lake => &[],
+            Biome::Beach => &[SpawnEntry { kind: PropKind::Rock, chance: 0.02 }],
+            Biome::Plains => &[
+                SpawnEntry { kind: PropKind::Rock, chance: 0.02 },
+                SpawnEntry { kind: PropKind::Tree, chance: 0.03 },
+                SpawnEntry { kind: PropKind::Sapling, chance: 0.04 },
+            ],
+            Biome::Forest => &[
+                SpawnEntry { kind: PropKind::Tree, chance: 0.25 },
+                SpawnEntry { kind: PropKind::Sapling, chance: 0.1 },
+                SpawnEntry { kind: PropKind::Rock, chance: 0.01 },
+            ],
+            Biome::Desert => &[SpawnEntry { kind: PropKind::Rock, chance: 0.04 }],
+            Biome::Highlands => &[
+                SpawnEntry { kind: PropKind::Rock, chance: 0.15 },
+                SpawnEntry { kind: PropKind::Sapling, chance: 0.01 },
+            ],
+        }
+    }
+
+    /// Creatures that can spawn in the biome, at most one spawns per tile.
+    pub fn creatures(&self) -> &'static [SpawnEntry<CreatureKind>]
+    {
+        match self
+        {
+            Biome::Ocean | Biome::River | Biome::Lake | Biome::Highlands => &[],
+            // fish men stay close to the water
+            Biome::Beach => &[SpawnEntry { kind: CreatureKind::FishMan, chance: 0.02 }],
+            Biome::Plains => &[SpawnEntry { kind: CreatureKind::FishMan, chance: 0.003 }],
+            Biome::Forest => &[SpawnEntry { kind: CreatureKind::FishMan, chance: 0.006 }],
+            Biome::Desert => &[SpawnEntry { kind: CreatureKind::FishMan, chance: 0.001 }],
+        }
+    }
+
+    /// Returns the kind of water the biome is made of, if any.
+    pub fn water_body(&self) -> Option<WaterBody>
+    {
+        match self
+        {
+            Biome::Ocean => Some(WaterBody::Ocean),
+            Biome::River => Some(WaterBody::River),
+            Biome::Lake => Some(WaterBody::Lake),
+            _ => None,
+        }
+    }
+}
+
+impl WaterBody
+{
+    /// Fish that bite in this kind of water, the last entry always succeeds.
+    pub fn fish(&self) -> &'static [SpawnEntry<FishKind>]
+    {
+        match self
+        {
+            WaterBody::River => &[
+                SpawnEntry { kind: FishKind::Salmon, chance: 0.3 },
+                SpawnEntry { kind: FishKind::Trout, chance: 1.0 },
+            ],
+            WaterBody::Lake => &[
+                SpawnEntry { kind: FishKind::Pike, chance: 0.15 },
+                SpawnEntry { kind: FishKind::Perch, chance: 0.4 },
+                SpawnEntry { kind: FishKind::Carp, chance: 1.0 },
+            ],
+            WaterBody::Ocean => &[
+                SpawnEntry { kind: FishKind::Pufferfish, chance: 0.05 },
+                SpawnEntry { kind: FishKind::Tuna, chance: 0.2 },
+                SpawnEntry { kind: FishKind::Cod, chance: 1.0 },
+            ],
+        }
+    }
+}
+
+impl FishKind
+{
+    /// Experience given for catching the fish.
+    pub fn xp(&self) -> u32
+    {
+        match self
+        {
+            FishKind::Trout | FishKind::Carp | FishKind::Cod => 500,
+            FishKind::Perch => 400,
+            FishKind::Salmon | FishKind::Tuna => 700,
+            FishKind::Pike => 800,
+            FishKind::Pufferfish => 1000,
+        }
+    }
+}
+
+/// Rolls once against every entry of `table` in order and returns the first that succeeds.
+pub fn roll<T: Copy>(table: &[SpawnEntry<T>], rng: &mut impl rand::Rng) -> Option<T>
+{
+    table
+        .iter()
+        .find(|entry| rng.gen_bool(entry.chance))
+        .map(|entry| entry.kind)
+}
@@ -1,8 +1,64 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Clone, Copy, PartialEq, Debug)]
+use super::biome::CreatureKind;
+use super::coords::ChunkPos;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum TileType
 {
     Grass,
-    Water,
+    ShallowWater,
+    DeepWater,
+    Sand,
+    ForestFloor,
+    Rock,
+    /// Water filled in by the player.
+    Dirt,
+    /// Planks laid over water by the player, can be walked on.
+    Bridge,
+}
+
+impl TileType
+{
+    pub const ALL: [TileType; 8] = [
+        TileType::Grass,
+        TileType::ShallowWater,
+        TileType::DeepWater,
+        TileType::Sand,
+        TileType::ForestFloor,
+        TileType::Rock,
+        TileType::Dirt,
+        TileType::Bridge,
+    ];
+
+    /// Returns true if the tile is covered by water.
+    /// Water tiles block walking and can be fished in.
+    pub fn is_water(&self) -> bool
+    {
+        matches!(self, TileType::ShallowWater | TileType::DeepWater)
+    }
+
+    /// Returns true if the tile is solid ground, bridges stand over water and aren't land.
+    /// Water next to land gets a shore.
+    pub fn is_land(&self) -> bool
+    {
+        !self.is_water() && *self != TileType::Bridge
+    }
 }
+
+/// A prop placed on a tile by the world generation, despawned along with its chunk.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Prop;
+
+/// A creature living in the world. It is parked when its chunk unloads and comes back with the chunk.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Creature(pub CreatureKind);
+
+/// The mesh drawing every tile of the chunk at the given position.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ChunkMesh(pub ChunkPos);
+
+/// A collider keeping units out of the water of the chunk at the given position.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WaterCollider(pub ChunkPos);
@@ -0,0 +1,212 @@
+//! Coordinates used by the world.
+//!
+//! - [`WorldPos`] is a position on the ground plane, in world units (the `x` and `z` of a `Transform`).
+//! - [`TilePos`] identifies a tile, tile `(0, 0)` is centered on the world origin.
+//! - [`ChunkPos`] identifies a chunk of `CHUNK_SIDE * CHUNK_SIDE` tiles, chunk `(0, 0)` is centered on tile `(0, 0)`.
+//!
+//! Every conversion rounds towards negative infinity, so negative coordinates behave like positive ones.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{CHUNK_RADIUS, CHUNK_SIDE};
+
+/// Size of a tile side in world units.
+pub const TILE_SIZE: f32 = 2.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct WorldPos
+{
+    pub x: f32,
+    pub z: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct TilePos
+{
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub struct ChunkPos
+{
+    pub x: i32,
+    pub y: i32,
+}
+
+impl WorldPos
+{
+    pub fn new(x: f32, z: f32) -> Self
+    {
+        Self { x, z }
+    }
+
+    /// Returns the tile containing this position.
+    pub fn tile(self) -> TilePos
+    {
+        TilePos::new(
+            (self.x / TILE_SIZE + 0.5).floor() as i32,
+            (self.z / TILE_SIZE + 0.5).floor() as i32,
+        )
+    }
+
+    /// Returns the chunk containing this position.
+    pub fn chunk(self) -> ChunkPos
+    {
+        self.tile().chunk()
+    }
+
+    /// Returns the translation of this position at the given height.
+    pub fn with_height(self, y: f32) -> Vec3
+    {
+        Vec3::new(self.x, y, self.z)
+    }
+}
+
+impl From<Vec3> for WorldPos
+{
+    fn from(translation: Vec3) -> Self
+    {
+        Self::new(translation.x, translation.z)
+    }
+}
+
+impl TilePos
+{
+    pub fn new(x: i32, y: i32) -> Self
+    {
+        Self { x, y }
+    }
+
+    /// Returns the position of the center of the tile.
+    pub fn center(self) -> WorldPos
+    {
+        WorldPos::new(self.x as f32 * TILE_SIZE, self.y as f32 * TILE_SIZE)
+    }
+
+    /// Returns the chunk containing the tile.
+    pub fn chunk(self) -> ChunkPos
+    {
+        ChunkPos::new(
+            (self.x + CHUNK_RADIUS).div_euclid(CHUNK_SIDE),
+            (self.y + CHUNK_RADIUS).div_euclid(CHUNK_SIDE),
+        )
+    }
+
+    /// Returns the index of the tile inside its chunk's tile array.
+    pub fn local(self) -> (usize, usize)
+    {
+        (
+            (self.x + CHUNK_RADIUS).rem_euclid(CHUNK_SIDE) as usize,
+            (self.y + CHUNK_RADIUS).rem_euclid(CHUNK_SIDE) as usize,
+        )
+    }
+
+    pub fn offset(self, dx: i32, dy: i32) -> Self
+    {
+        Self::new(self.x + dx, self.y + dy)
+    }
+}
+
+impl ChunkPos
+{
+    pub fn new(x: i32, y: i32) -> Self
+    {
+        Self { x, y }
+    }
+
+    /// Returns the tile at the given index of the chunk's tile array.
+    pub fn tile(self, local: (usize, usize)) -> TilePos
+    {
+        TilePos::new(
+            self.x * CHUNK_SIDE - CHUNK_RADIUS + local.0 as i32,
+            self.y * CHUNK_SIDE - CHUNK_RADIUS + local.1 as i32,
+        )
+    }
+
+    /// Returns the tile in the middle of the chunk.
+    pub fn center(self) -> TilePos
+    {
+        TilePos::new(self.x * CHUNK_SIDE, self.y * CHUNK_SIDE)
+    }
+
+    pub fn offset(self, dx: i32, dy: i32) -> Self
+    {
+        Self::new(self.x + dx, self.y + dy)
+    }
+
+    /// Returns the number of chunks between the two chunks, diagonals included.
+    pub fn distance(self, other: ChunkPos) -> i32
+    {
+        (self.x - other.x).abs().max((self.y - other.y).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn positions_round_to_tiles_on_both_sides_of_zero()
+    {
+        let tile_x = |x: f32| WorldPos::new(x, 0.).tile().x;
+        assert_eq!(tile_x(0.), 0);
+        assert_eq!(tile_x(0.99), 0);
+        assert_eq!(tile_x(1.), 1);
+        assert_eq!(tile_x(-1.), 0);
+        assert_eq!(tile_x(-1.01), -1);
+        assert_eq!(tile_x(-3.), -1);
+        assert_eq!(tile_x(-3.01), -2);
+        assert_eq!(WorldPos::new(-1.01, 1.).tile(), TilePos::new(-1, 1));
+    }
+
+    #[test]
+    fn tiles_belong_to_chunks_on_both_sides_of_zero()
+    {
+        let cases = [
+            (-8, -2, 4),
+            (-7, -1, 0),
+            (-3, -1, 4),
+            (-2, 0, 0),
+            (0, 0, 2),
+            (2, 0, 4),
+            (3, 1, 0),
+            (7, 1, 4),
+        ];
+        for (x, chunk, local) in cases
+        {
+            let tile = TilePos::new(x, -x);
+            assert_eq!(tile.chunk(), ChunkPos::new(chunk, -chunk), "chunk of tile {}", x);
+            let (i, _) = tile.local();
+            assert_eq!(i, local, "index of tile {}", x);
+        }
+    }
+
+    #[test]
+    fn positions_belong_to_chunks_on_both_sides_of_zero()
+    {
+        assert_eq!(WorldPos::new(-5., 5.).chunk(), ChunkPos::new(0, 1));
+        assert_eq!(WorldPos::new(-5.01, 4.99).chunk(), ChunkPos::new(-1, 0));
+    }
+
+    #[test]
+    fn chunk_tiles_round_trip()
+    {
+        for chunk_pos in [ChunkPos::new(0, 0), ChunkPos::new(-1, 2), ChunkPos::new(3, -4)]
+        {
+            for i in 0..CHUNK_SIDE as usize
+            {
+                for j in 0..CHUNK_SIDE as usize
+                {
+                    let tile = chunk_pos.tile((i, j));
+                    assert_eq!(tile.local(), (i, j));
+                    assert_eq!(tile.chunk(), chunk_pos);
+                    assert_eq!(tile.center().tile(), tile);
+                }
+            }
+            assert_eq!(chunk_pos.tile((CHUNK_RADIUS as usize, CHUNK_RADIUS as usize)), chunk_pos.center());
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+
+use super::biome::CreatureKind;
+use super::coords::{ChunkPos, TilePos};
+
+/// Sent when a chunk is loaded for the first time, for every creature its biome spawned,
+/// and when it is loaded again, for every creature parked in it.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SpawnCreature
+{
+    pub kind: CreatureKind,
+    pub translation: Vec3,
+    /// Health left to a creature that was parked when its chunk unloaded.
+    /// It comes back alone, not as a new group. `None` for creatures spawning for the first time.
+    pub health: Option<u32>,
+}
+
+/// Sent when the tiles of a loaded chunk or of its neighbours changed, so its mesh gets rebuilt.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RefreshChunk(pub ChunkPos);
+
+/// Ways the player can change a tile.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TerrainEdit
+{
+    /// Turns land, or a bridge, into shallow water.
+    Dig,
+    /// Turns water into dirt.
+    Fill,
+    /// Lays a bridge over water.
+    Bridge,
+}
+
+/// Sent to change the tile at `tile`. Edits that don't apply to the tile are ignored.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EditTerrain
+{
+    pub tile: TilePos,
+    pub edit: TerrainEdit,
+}
@@ -0,0 +1,213 @@
+//! Writes a picture and a text dump of a region of the world, without opening a window.
+//!
+//! ```text
+//! cargo run -- export-map --seed 1234 --region=-4,-4,4,4 --out map
+//! ```
+//!
+//! `region` is the first and last chunk to export, as `min_x,min_y,max_x,max_y`.
+//! It writes `map.png`, with `scale` pixels per tile, and `map.txt`, with one character per tile.
+
+use std::fs;
+
+use image::{Rgba, RgbaImage};
+
+use super::biome::PropKind;
+use super::components::TileType;
+use super::coords::ChunkPos;
+use super::generation::WorldGenerator;
+use super::resources::{Chunk, Generate, WorldSeed};
+use super::structures::{load_templates, structures_dir};
+use super::CHUNK_SIDE;
+
+/// Name of the subcommand that runs the export instead of the game.
+pub const EXPORT_COMMAND: &str = "export-map";
+/// Chunks exported when no `region` is given.
+const DEFAULT_REGION: (ChunkPos, ChunkPos) = (ChunkPos { x: -4, y: -4 }, ChunkPos { x: 4, y: 4 });
+const DEFAULT_OUT: &str = "map";
+const DEFAULT_SCALE: u32 = 4;
+
+/// Returns true if the game was started to export a map.
+pub fn is_requested() -> bool
+{
+    std::env::args().nth(1).is_some_and(|arg| arg == EXPORT_COMMAND)
+}
+
+/// Generates the region given on the command line and writes it to the output files.
+pub fn run() -> Result<(), String>
+{
+    let seed = WorldSeed::from_config(None);
+    let (min, max) = match crate::config::get("region")
+    {
+        Some(region) => parse_region(&region)?,
+        None => DEFAULT_REGION,
+    };
+    let out = crate::config::get("out").unwrap_or_else(|| DEFAULT_OUT.to_string());
+    let scale = crate::config::parse("scale").unwrap_or(DEFAULT_SCALE).max(1);
+
+    let generator = WorldGenerator::new(seed, load_templates(&structures_dir()));
+    let map = Map::generate(&generator, min, max);
+
+    let png = format!("{}.png", out);
+    map.to_image(scale)
+        .save(&png)
+        .map_err(|error| format!("Could not write {}: {}", png, error))?;
+    let txt = format!("{}.txt", out);
+    fs::write(&txt, map.to_ascii()).map_err(|error| format!("Could not write {}: {}", txt, error))?;
+
+    println!(
+        "Exported chunks ({}, {}) to ({}, {}) of seed {} to {} and {}",
+        min.x, min.y, max.x, max.y, seed.0, png, txt
+    );
+    Ok(())
+}
+
+/// Reads a region written as `min_x,min_y,max_x,max_y`.
+fn parse_region(region: &str) -> Result<(ChunkPos, ChunkPos), String>
+{
+    let values = region
+        .split(',')
+        .map(|value| value.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format!("Invalid region {}: {}", region, error))?;
+    match values[..]
+    {
+        [x0, y0, x1, y1] => Ok((
+            ChunkPos::new(x0.min(x1), y0.min(y1)),
+            ChunkPos::new(x0.max(x1), y0.max(y1)),
+        )),
+        _ => Err(format!("Invalid region {}, expected min_x,min_y,max_x,max_y", region)),
+    }
+}
+
+/// Tiles and props of a rectangle of chunks, indexed by column then row from the top left tile.
+struct Map
+{
+    tiles: Vec<Vec<TileType>>,
+    props: Vec<Vec<Option<PropKind>>>,
+}
+
+impl Map
+{
+    fn generate(generator: &WorldGenerator, min: ChunkPos, max: ChunkPos) -> Self
+    {
+        let side = CHUNK_SIDE as usize;
+        let columns = (max.x - min.x + 1) as usize * side;
+        let rows = (max.y - min.y + 1) as usize * side;
+        let mut tiles = vec![vec![TileType::Grass; rows]; columns];
+        let mut props = vec![vec![None; rows]; columns];
+
+        for x in min.x..=max.x
+        {
+            for y in min.y..=max.y
+            {
+                let chunk_pos = ChunkPos::new(x, y);
+                let chunk = Chunk::generate(generator, chunk_pos);
+                let first = ((x - min.x) as usize * side, (y - min.y) as usize * side);
+                for i in 0..side
+                {
+                    for j in 0..side
+                    {
+                        tiles[first.0 + i][first.1 + j] = chunk.tiles[i][j];
+                        props[first.0 + i][first.1 + j] = chunk.prop_at((i, j));
+                    }
+                }
+            }
+        }
+        Self { tiles, props }
+    }
+
+    fn size(&self) -> (usize, usize)
+    {
+        (self.tiles.len(), self.tiles.first().map_or(0, Vec::len))
+    }
+
+    /// Draws every tile as a square of `scale` pixels, props as a dot in its middle.
+    fn to_image(&self, scale: u32) -> RgbaImage
+    {
+        let (columns, rows) = self.size();
+        let mut image = RgbaImage::new(columns as u32 * scale, rows as u32 * scale);
+        let dot = scale / 4..scale - scale / 4;
+        for (x, y, pixel) in image.enumerate_pixels_mut()
+        {
+            let (column, row) = ((x / scale) as usize, (y / scale) as usize);
+            let (inner_x, inner_y) = (x % scale, y % scale);
+            let in_dot = dot.contains(&inner_x) && dot.contains(&inner_y);
+            *pixel = match self.props[column][row]
+            {
+                Some(prop) if in_dot => prop_color(prop),
+                _ => tile_color(self.tiles[column][row]),
+            };
+        }
+        image
+    }
+
+    /// One line per row of tiles, props are drawn over the tile they stand on.
+    fn to_ascii(&self) -> String
+    {
+        let (columns, rows) = self.size();
+        let mut ascii = String::with_capacity((columns + 1) * rows);
+        for row in 0..rows
+        {
+            for column in 0..columns
+            {
+                ascii.push(match self.props[column][row]
+                {
+                    Some(prop) => prop_char(prop),
+                    None => tile_char(self.tiles[column][row]),
+                });
+            }
+            ascii.push('\n');
+        }
+        ascii
+    }
+}
+
+fn tile_color(tile_type: TileType) -> Rgba<u8>
+{
+    match tile_type
+    {
+        TileType::Grass => Rgba([106, 190, 48, 255]),
+        TileType::ShallowWater => Rgba([91, 110, 225, 255]),
+        TileType::DeepWater => Rgba([48, 64, 160, 255]),
+        TileType::Sand => Rgba([217, 190, 130, 255]),
+        TileType::ForestFloor => Rgba([55, 120, 40, 255]),
+        TileType::Rock => Rgba([132, 126, 135, 255]),
+        TileType::Dirt => Rgba([120, 82, 44, 255]),
+        TileType::Bridge => Rgba([150, 102, 60, 255]),
+    }
+}
+
+fn prop_color(kind: PropKind) -> Rgba<u8>
+{
+    match kind
+    {
+        PropKind::Rock => Rgba([80, 76, 82, 255]),
+        PropKind::Tree => Rgba([30, 70, 25, 255]),
+        PropKind::Sapling => Rgba([150, 220, 90, 255]),
+    }
+}
+
+fn tile_char(tile_type: TileType) -> char
+{
+    match tile_type
+    {
+        TileType::Grass => '.',
+        TileType::ShallowWater => '~',
+        TileType::DeepWater => 'W',
+        TileType::Sand => ':',
+        TileType::ForestFloor => ',',
+        TileType::Rock => '^',
+        TileType::Dirt => '_',
+        TileType::Bridge => '=',
+    }
+}
+
+fn prop_char(kind: PropKind) -> char
+{
+    match kind
+    {
+        PropKind::Rock => 'o',
+        PropKind::Tree => 'T',
+        PropKind::Sapling => 't',
+    }
+}
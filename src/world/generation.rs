@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use noise::{NoiseFn, Perlin};
+
+use super::biome::{Biome, WaterBody, HIGHLANDS_LEVEL};
+use super::coords::TilePos;
+use super::resources::WorldSeed;
+use super::structures::StructureTemplate;
+
+/// Distance between two neighbouring tiles in noise space.
+pub const NOISE_SCALE: f64 = 0.1;
+/// Distance between two neighbouring tiles in the temperature and moisture noise.
+/// Smaller than `NOISE_SCALE` so biomes span many islands.
+pub const CLIMATE_SCALE: f64 = 0.02;
+/// Radius of the island guaranteed around the spawn point, in noise space.
+pub const SPAWN_ISLAND_RADIUS: f64 = 1.0;
+/// Highest elevation of the spawn island, keeps it from turning into highlands.
+pub const SPAWN_ISLAND_HEIGHT: f64 = 0.3;
+/// Distance between two neighbouring tiles in the lake noise.
+pub const LAKE_SCALE: f64 = 0.05;
+/// Value of the lake noise above which land is flooded.
+pub const LAKE_LEVEL: f64 = 0.45;
+
+/// Samples the world's noise functions directly at tile coordinates.
+/// Nothing is baked up front, so the world is unbounded and every tile only depends on the seed.
+#[derive(Clone)]
+pub struct WorldGenerator
+{
+    pub seed: WorldSeed,
+    elevation: Perlin,
+    temperature: Perlin,
+    moisture: Perlin,
+    lakes: Perlin,
+    /// Templates of the structures that can be placed, shared with the generation tasks.
+    pub structures: Arc<Vec<StructureTemplate>>,
+}
+
+impl WorldGenerator
+{
+    pub fn new(seed: WorldSeed, structures: Vec<StructureTemplate>) -> Self
+    {
+        Self {
+            seed,
+            elevation: Perlin::new(seed.0),
+            temperature: Perlin::new(seed.0.wrapping_add(1)),
+            moisture: Perlin::new(seed.0.wrapping_add(2)),
+            lakes: Perlin::new(seed.0.wrapping_add(4)),
+            structures: Arc::new(structures),
+        }
+    }
+
+    /// Returns the elevation of the tile.
+    /// Negative values are under water.
+    pub fn elevation(&self, tile: TilePos) -> f64
+    {
+        let point = [tile.x as f64 * NOISE_SCALE, tile.y as f64 * NOISE_SCALE];
+        // keep a circle of land around the spawn point
+        let island = SPAWN_ISLAND_RADIUS.powi(2) - (point[0].powi(2) + point[1].powi(2));
+        island.min(SPAWN_ISLAND_HEIGHT).max(self.elevation.get(point))
+    }
+
+    /// Returns the temperature and moisture of the tile.
+    pub fn climate(&self, tile: TilePos) -> (f64, f64)
+    {
+        let point = [tile.x as f64 * CLIMATE_SCALE, tile.y as f64 * CLIMATE_SCALE];
+        (self.temperature.get(point), self.moisture.get(point))
+    }
+
+    /// Returns the biome of the tile, along with its elevation.
+    /// Rivers are left out, they are traced over whole regions by [`WorldGenerator::river_tiles`].
+    pub fn biome(&self, tile: TilePos) -> (Biome, f64)
+    {
+        let elevation = self.elevation(tile);
+        let biome = match self.water_body(tile, elevation)
+        {
+            Some(WaterBody::Lake) => Biome::Lake,
+            _ =>
+            {
+                let (temperature, moisture) = self.climate(tile);
+                Biome::from_climate(elevation, temperature, moisture)
+            }
+        };
+        (biome, elevation)
+    }
+
+    /// Returns the sea or lake covering the tile, if any.
+    /// Every check only looks at the tile itself, so lakes run on across chunk borders.
+    pub fn water_body(&self, tile: TilePos, elevation: f64) -> Option<WaterBody>
+    {
+        if elevation < 0.0
+        {
+            return Some(WaterBody::Ocean);
+        }
+        // keep the spawn island dry
+        let point = [tile.x as f64 * NOISE_SCALE, tile.y as f64 * NOISE_SCALE];
+        if point[0].powi(2) + point[1].powi(2) < SPAWN_ISLAND_RADIUS.powi(2)
+        {
+            return None;
+        }
+
+        let lake_point = [tile.x as f64 * LAKE_SCALE, tile.y as f64 * LAKE_SCALE];
+        if elevation < HIGHLANDS_LEVEL && self.lakes.get(lake_point) > LAKE_LEVEL
+        {
+            return Some(WaterBody::Lake);
+        }
+        None
+    }
+}
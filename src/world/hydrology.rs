@@ -0,0 +1,197 @@
+//! Rivers traced downhill over the elevation field.
+//!
+//! The world is split in square cells of `RIVER_CELL` tiles, each of which may hold the source of a river.
+//! From its source up in the hills, a river always steps to its lowest neighbour until it reaches the sea
+//! or a lake. A river stuck in a hollow, or that runs too long, pools into a small lake where it stops.
+//! Rivers only depend on the seed, so every chunk traces the same rivers and they run on across chunk borders.
+//! Two rivers reaching the same tile follow the same way down from there, so they merge instead of crossing.
+
+use bevy::math::IVec2;
+use bevy::utils::HashMap;
+use rand::Rng;
+
+use super::coords::{ChunkPos, TilePos};
+use super::generation::{WorldGenerator, NOISE_SCALE, SPAWN_ISLAND_RADIUS};
+use super::CHUNK_RADIUS;
+
+/// Side of the square of tiles that holds at most one river source.
+pub const RIVER_CELL: i32 = 16;
+/// Chance of a cell holding a river source.
+pub const RIVER_CHANCE: f64 = 0.8;
+/// Tiles of a cell tried as its source, the highest one is kept.
+const SOURCE_CANDIDATES: usize = 4;
+/// Elevation a source needs, rivers spring up in the hills.
+pub const RIVER_SOURCE_LEVEL: f64 = 0.3;
+/// Most tiles a river runs over before it pools into a lake.
+pub const MAX_RIVER_LENGTH: usize = 32;
+/// Tiles a river runs over before it is wide enough to cover a tile on each of its banks.
+pub const RIVER_WIDEN_AFTER: usize = 8;
+
+/// Rivers step to one of their four direct neighbours, so river tiles always share an edge.
+const STEPS: [(i32, i32); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+/// A river from its source down to where it ends.
+#[derive(Clone, Debug)]
+pub struct River
+{
+    /// Tiles the river runs over, from the source downhill. Each tile flows into the next one.
+    pub path: Vec<TilePos>,
+    /// The river ends in the sea or a lake, its last tile flows into `mouth`.
+    /// `None` if it ends in a hollow, where it pools around its last tile.
+    pub mouth: Option<TilePos>,
+}
+
+/// Water a river brings to a land tile.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RiverTile
+{
+    /// Running water, flowing into the neighbour at the given offset.
+    Flow(IVec2),
+    /// Water pooled at the end of a river that didn't reach the sea or a lake.
+    Pool,
+}
+
+impl River
+{
+    /// Returns the offset from the tile at `index` in the path to the tile it flows into.
+    fn direction(&self, index: usize) -> Option<IVec2>
+    {
+        let next = self.path.get(index + 1).copied().or(self.mouth)?;
+        let tile = self.path[index];
+        Some(IVec2::new(next.x - tile.x, next.y - tile.y))
+    }
+}
+
+impl WorldGenerator
+{
+    /// Returns the river springing from the cell, if the cell has one.
+    /// Only depends on the seed and the cell, so every chunk agrees on where rivers run.
+    pub fn river_from(&self, cell: IVec2) -> Option<River>
+    {
+        let mut rng = self.seed.river_rng(cell);
+        if !rng.gen_bool(RIVER_CHANCE)
+        {
+            return None;
+        }
+        let (source, mut elevation) = (0..SOURCE_CANDIDATES)
+            .map(|_| {
+                TilePos::new(
+                    cell.x * RIVER_CELL + rng.gen_range(0..RIVER_CELL),
+                    cell.y * RIVER_CELL + rng.gen_range(0..RIVER_CELL),
+                )
+            })
+            .map(|tile| (tile, self.elevation(tile)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if elevation < RIVER_SOURCE_LEVEL || self.water_body(source, elevation).is_some()
+        {
+            return None;
+        }
+
+        let mut path = vec![source];
+        let mut mouth = None;
+        while path.len() < MAX_RIVER_LENGTH
+        {
+            let tile = path[path.len() - 1];
+            // steepest descent, elevation goes down at every step so a river never loops
+            let Some((lowest, lowest_elevation)) = STEPS
+                .iter()
+                .map(|(dx, dy)| tile.offset(*dx, *dy))
+                .map(|neighbour| (neighbour, self.elevation(neighbour)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .filter(|(_, lowest_elevation)| *lowest_elevation < elevation)
+            else
+            {
+                break;
+            };
+            if self.water_body(lowest, lowest_elevation).is_some()
+            {
+                mouth = Some(lowest);
+                break;
+            }
+            path.push(lowest);
+            elevation = lowest_elevation;
+        }
+
+        // the player must not start in a river
+        let near_spawn = path.iter().any(|tile| {
+            let (x, y) = (tile.x as f64 * NOISE_SCALE, tile.y as f64 * NOISE_SCALE);
+            x.powi(2) + y.powi(2) < SPAWN_ISLAND_RADIUS.powi(2)
+        });
+        if near_spawn
+        {
+            return None;
+        }
+        Some(River { path, mouth })
+    }
+
+    /// Returns every river that may run over a tile of the chunk.
+    pub fn rivers_near(&self, chunk_pos: ChunkPos) -> Vec<River>
+    {
+        // a river reaches at most its length away from its source, plus a bank or a pool
+        let reach = MAX_RIVER_LENGTH as i32 + 1;
+        let center = chunk_pos.center();
+        let cell = |tile: i32| tile.div_euclid(RIVER_CELL);
+        let (min_x, max_x) = (cell(center.x - CHUNK_RADIUS - reach), cell(center.x + CHUNK_RADIUS + reach));
+        let (min_y, max_y) = (cell(center.y - CHUNK_RADIUS - reach), cell(center.y + CHUNK_RADIUS + reach));
+        (min_x..=max_x)
+            .flat_map(|x| (min_y..=max_y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.river_from(cell))
+            .collect()
+    }
+
+    /// Returns the water rivers bring to the tiles of the chunk, by tile.
+    pub fn river_tiles(&self, chunk_pos: ChunkPos) -> HashMap<TilePos, RiverTile>
+    {
+        let in_chunk = |tile: &TilePos| tile.chunk() == chunk_pos;
+        let rivers = self.rivers_near(chunk_pos);
+        let mut tiles = HashMap::default();
+
+        // pools first, then banks, so the river bed itself always keeps its flow
+        for river in rivers.iter().filter(|river| river.mouth.is_none())
+        {
+            let end = river.path[river.path.len() - 1];
+            for tile in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| end.offset(dx, dy)))
+            {
+                if in_chunk(&tile)
+                {
+                    tiles.insert(tile, RiverTile::Pool);
+                }
+            }
+        }
+        for river in rivers.iter()
+        {
+            for index in RIVER_WIDEN_AFTER..river.path.len()
+            {
+                let Some(direction) = river.direction(index)
+                else
+                {
+                    continue;
+                };
+                let tile = river.path[index];
+                let bank = direction.perp();
+                for bank_tile in [tile.offset(bank.x, bank.y), tile.offset(-bank.x, -bank.y)]
+                {
+                    if in_chunk(&bank_tile)
+                    {
+                        tiles.entry(bank_tile).or_insert(RiverTile::Flow(direction));
+                    }
+                }
+            }
+        }
+        for river in rivers.iter()
+        {
+            for (index, tile) in river.path.iter().enumerate()
+            {
+                if !in_chunk(tile)
+                {
+                    continue;
+                }
+                if let Some(direction) = river.direction(index)
+                {
+                    tiles.insert(*tile, RiverTile::Flow(direction));
+                }
+            }
+        }
+        tiles
+    }
+}
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+
+use super::autotile::shore_mask;
+use super::components::TileType;
+use super::coords::{ChunkPos, TilePos, TILE_SIZE};
+use super::resources::{Chunk, TileAtlas, WorldSeed};
+use super::CHUNK_SIDE;
+
+/// A rectangle of tiles inside a chunk, in tile array indices.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileRect
+{
+    pub start: (usize, usize),
+    pub size: (usize, usize),
+}
+
+/// Builds a single mesh holding every tile of the chunk, textured from the tile atlas.
+/// The mesh lies flat on the ground and is centered on the chunk's middle tile.
+/// `tile_at` looks up the tiles of the neighbouring chunks, to draw shores across chunk borders.
+/// Tile variants are picked from the seed and the tile's position, so they stay the same across reloads.
+pub fn build_chunk_mesh(
+    chunk: &Chunk,
+    chunk_pos: ChunkPos,
+    atlas: &TileAtlas,
+    seed: &WorldSeed,
+    tile_at: impl Fn(TilePos) -> Option<TileType>,
+) -> Mesh
+{
+    let neighbour = |tile: TilePos| {
+        if tile.chunk() == chunk_pos
+        {
+            let (i, j) = tile.local();
+            Some(chunk.tiles[i][j])
+        }
+        else
+        {
+            tile_at(tile)
+        }
+    };
+    let middle = chunk_pos.center().center();
+    let tile_count = (CHUNK_SIDE * CHUNK_SIDE) as usize;
+    let mut positions = Vec::with_capacity(tile_count * 4);
+    let mut normals = Vec::with_capacity(tile_count * 4);
+    let mut uvs = Vec::with_capacity(tile_count * 4);
+    let mut indices = Vec::with_capacity(tile_count * 6);
+    let half = TILE_SIZE / 2.;
+
+    for (i, row) in chunk.tiles.iter().enumerate()
+    {
+        for (j, tile) in row.iter().enumerate()
+        {
+            let tile_pos = chunk_pos.tile((i, j));
+            let center = tile_pos.center();
+            let (x, z) = (center.x - middle.x, center.z - middle.z);
+            let mask = shore_mask(*tile, tile_pos, &neighbour);
+            let uv = atlas.uv(*tile, mask, seed.hash(tile_pos.x, tile_pos.y));
+
+            let first = positions.len() as u32;
+            // the top of the texture faces away from the camera, like the tile sprites did
+            positions.extend([
+                [x - half, 0., z - half],
+                [x + half, 0., z - half],
+                [x + half, 0., z + half],
+                [x - half, 0., z + half],
+            ]);
+            normals.extend([[0., 1., 0.]; 4]);
+            uvs.extend([
+                [uv.min.x, uv.min.y],
+                [uv.max.x, uv.min.y],
+                [uv.max.x, uv.max.y],
+                [uv.min.x, uv.max.y],
+            ]);
+            indices.extend([first, first + 2, first + 1, first, first + 3, first + 2]);
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// Covers the water tiles of the chunk with as few rectangles as it can,
+/// so each rectangle can be a single collider.
+pub fn water_rects(chunk: &Chunk) -> Vec<TileRect>
+{
+    let side = CHUNK_SIDE as usize;
+    let mut covered = [[false; CHUNK_SIDE as usize]; CHUNK_SIDE as usize];
+    let mut rects = vec![];
+
+    for i in 0..side
+    {
+        for j in 0..side
+        {
+            if covered[i][j] || !chunk.tiles[i][j].is_water()
+            {
+                continue;
+            }
+            // grow along j first, then along i as long as the whole span is water
+            let mut height = 1;
+            while j + height < side && !covered[i][j + height] && chunk.tiles[i][j + height].is_water()
+            {
+                height += 1;
+            }
+            let mut width = 1;
+            while i + width < side
+                && (j..j + height).all(|y| !covered[i + width][y] && chunk.tiles[i + width][y].is_water())
+            {
+                width += 1;
+            }
+            for x in i..i + width
+            {
+                for y in j..j + height
+                {
+                    covered[x][y] = true;
+                }
+            }
+            rects.push(TileRect {
+                start: (i, j),
+                size: (width, height),
+            });
+        }
+    }
+
+    rects
+}
@@ -1,53 +1,69 @@
-use std::cmp::min;
-
 use crate::GameState;
 use bevy::prelude::*;
-use bevy::utils::HashMap;
-use noise::core::perlin::perlin_2d;
-use noise::permutationtable::PermutationTable;
-use noise::utils::{NoiseMapBuilder, PlaneMapBuilder};
-use noise::{Abs, Curve, Cylinders, Fbm, NoiseFn, Perlin, ScalePoint};
-use rand::RngCore;
 
+pub mod autotile;
+pub mod biome;
 pub mod components;
+pub mod coords;
+pub mod events;
+pub mod export;
+pub mod generation;
+pub mod hydrology;
+pub mod mesh;
 pub mod resources;
+pub mod save;
+pub mod structures;
 pub mod systems;
 
+use self::events::*;
+use self::generation::WorldGenerator;
 use self::resources::*;
+use self::save::*;
+use self::structures::{load_templates, structures_dir};
 use self::systems::*;
 
 pub const CHUNK_RADIUS: i32 = 2;
 pub const CHUNK_SIDE: i32 = CHUNK_RADIUS * 2 + 1;
-pub const MAP_SIDE: usize = 1000;
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        let mut rng = rand::thread_rng();
-        let perlin = Perlin::new(rng.next_u32());
-        let hasher = PermutationTable::new(0);
+        let save = SaveData::load(&save_path());
+        let seed = WorldSeed::from_config(save.as_ref().map(|save| save.seed));
+        let save = match save {
+            Some(save) if save.seed != seed.0 => {
+                warn!(
+                    "Ignoring save file made with seed {}, the world uses seed {}",
+                    save.seed, seed.0
+                );
+                None
+            }
+            save => save,
+        };
+        let generator = WorldGenerator::new(seed, load_templates(&structures_dir()));
 
-        app.insert_resource(WorldData {
-            chunks: HashMap::default(),
-            // make it so circle around player, yes
-            noise_map: PlaneMapBuilder::<_, 2>::new_fn(
-                |point, _hasher| {
-                    (-((point[0] + 50.).abs().min((point[0] - 50.).abs()).powi(2)
-                        + (point[1] + 50.).abs().min((point[1] - 50.).abs()).powi(2)
-                        - 1.))
-                        .max(perlin.get(point))
-                },
-                &hasher,
-            )
-            .set_is_seamless(false)
-            .set_size(MAP_SIDE, MAP_SIDE)
-            .set_x_bounds(-50.0, 50.0)
-            .set_y_bounds(-50.0, 50.0)
-            .build(),
-        })
+        app.insert_resource(seed);
+        app.register_type::<WorldSettings>();
+        app.insert_resource(WorldSettings::from_config());
+        app.insert_resource(save.unwrap_or_default().into_world(generator))
+        .add_event::<SpawnCreature>()
+        .add_event::<RefreshChunk>()
+        .add_event::<EditTerrain>()
+        .add_systems(OnEnter(GameState::Spawning), build_tile_atlas)
         .add_systems(OnEnter(GameState::Ready), spawn_tiles_around_player)
-        .add_systems(Update, update_tiles.run_if(in_state(GameState::Ready)))
-        .add_systems(Update, deload_chunks.run_if(in_state(GameState::Ready)));
+        .add_systems(
+            Update,
+            (
+                receive_generated_chunks,
+                update_tiles,
+                apply_terrain_edits,
+                refresh_chunk_meshes,
+            )
+                .chain()
+                .run_if(in_state(GameState::Ready)),
+        )
+        .add_systems(Update, deload_chunks.run_if(in_state(GameState::Ready)))
+        .add_systems(Last, save_world.run_if(in_state(GameState::Ready)));
     }
 }
@@ -1,21 +1,375 @@
-use bevy::{prelude::*, utils::HashMap};
-use noise::utils::NoiseMap;
+use std::collections::BTreeMap;
 
-use crate::world::MAP_SIDE;
+use bevy::tasks::Task;
+use bevy::{prelude::*, utils::{HashMap, HashSet}};
+use serde::{Deserialize, Serialize};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
 
+use super::biome::{self, Biome, CreatureKind, PropKind, WaterBody};
+use super::coords::{ChunkPos, TilePos, WorldPos};
+use super::generation::WorldGenerator;
+use super::hydrology::RiverTile;
+use super::structures::{PlacedStructure, StructureTemplate};
 use super::{components::TileType, CHUNK_SIDE};
 
+/// Seed that drives every random choice made while generating the world.
+/// The same seed always produces the same tiles.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct WorldSeed(pub u32);
+
+impl WorldSeed
+{
+    /// Reads the seed from the `seed` setting (command line or config file).
+    /// Falls back to the seed of the save file, then to a random seed,
+    /// which is logged so the world can be reproduced.
+    pub fn from_config(saved: Option<u32>) -> Self
+    {
+        match crate::config::parse::<u32>("seed").or(saved)
+        {
+            Some(seed) => Self(seed),
+            None =>
+            {
+                let seed = Self(rand::thread_rng().next_u32());
+                info!("No world seed given, using random seed {}", seed.0);
+                seed
+            }
+        }
+    }
+
+    /// Returns a random number generator for one-off choices made while setting up the world.
+    pub fn rng(&self) -> StdRng
+    {
+        StdRng::seed_from_u64(self.0 as u64)
+    }
+
+    /// Returns a random number generator unique to the chunk at `chunk_pos`.
+    /// Generating the same chunk twice always makes the same choices.
+    pub fn chunk_rng(&self, chunk_pos: ChunkPos) -> StdRng
+    {
+        StdRng::seed_from_u64(self.hash(chunk_pos.x, chunk_pos.y))
+    }
+
+    /// Returns a random number generator used to place structures in the chunk at `chunk_pos`.
+    /// Kept apart from `chunk_rng` so structures don't change the rest of the chunk.
+    pub fn structure_rng(&self, chunk_pos: ChunkPos) -> StdRng
+    {
+        StdRng::seed_from_u64(!self.hash(chunk_pos.x, chunk_pos.y))
+    }
+
+    /// Returns a random number generator used to place the origin of the structure in the chunk at `chunk_pos`.
+    /// Kept apart from `structure_rng` so the origin doesn't depend on how many templates were rolled for.
+    pub fn structure_origin_rng(&self, chunk_pos: ChunkPos) -> StdRng
+    {
+        StdRng::seed_from_u64(!self.hash(chunk_pos.x, chunk_pos.y).rotate_left(16))
+    }
+
+    /// Returns a random number generator used to place the river source of the cell at `cell`.
+    pub fn river_rng(&self, cell: IVec2) -> StdRng
+    {
+        StdRng::seed_from_u64(self.hash(cell.x, cell.y).rotate_left(32))
+    }
+
+    /// Mixes the seed with a pair of coordinates into a well distributed value.
+    pub fn hash(&self, x: i32, y: i32) -> u64
+    {
+        let mut h = (self.0 as u64).wrapping_mul(0x9e3779b97f4a7c15)
+            ^ ((x as u32 as u64) << 32 | y as u32 as u64);
+        // splitmix64 finalizer
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+        h ^ (h >> 31)
+    }
+}
+
+/// Controls how many chunks are kept around the player.
+/// Read every frame, so changes made at runtime take effect immediately.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct WorldSettings
+{
+    /// Chunks at most this many chunks away from the player's chunk are loaded.
+    pub load_radius: i32,
+    /// Extra distance a loaded chunk may get before it is unloaded,
+    /// so walking back and forth over a chunk border doesn't reload chunks.
+    pub unload_hysteresis: i32,
+    /// Most chunks generated and spawned in a single frame.
+    pub max_chunks_per_frame: usize,
+}
+
+impl Default for WorldSettings
+{
+    fn default() -> Self
+    {
+        Self {
+            load_radius: 1,
+            unload_hysteresis: 1,
+            max_chunks_per_frame: 2,
+        }
+    }
+}
+
+impl WorldSettings
+{
+    /// Reads the settings from the command line or config file, missing ones keep their default.
+    pub fn from_config() -> Self
+    {
+        let default = Self::default();
+        Self {
+            load_radius: crate::config::parse("load_radius").unwrap_or(default.load_radius),
+            unload_hysteresis: crate::config::parse("unload_hysteresis")
+                .unwrap_or(default.unload_hysteresis),
+            max_chunks_per_frame: crate::config::parse("max_chunks_per_frame")
+                .unwrap_or(default.max_chunks_per_frame),
+        }
+    }
+
+    /// Distance past which loaded chunks are unloaded.
+    pub fn unload_radius(&self) -> i32
+    {
+        self.load_radius + self.unload_hysteresis.max(0)
+    }
+}
+
+/// Every tile texture packed into a single image, shared by all chunk meshes.
+#[derive(Resource)]
+pub struct TileAtlas
+{
+    pub material: Handle<StandardMaterial>,
+    /// Areas of the atlas used by the variants of each tile type, in texture coordinates.
+    pub uvs: HashMap<TileType, Vec<TileVariant>>,
+    /// Area of the atlas used by each shore variant of the water tiles, by shore mask.
+    pub shores: HashMap<(TileType, u8), Rect>,
+}
+
+/// A way to draw a tile type, picked `weight` times out of the sum of its variants' weights.
+#[derive(Clone, Copy, Debug)]
+pub struct TileVariant
+{
+    pub rect: Rect,
+    pub weight: u32,
+}
+
+impl TileAtlas
+{
+    /// Returns the area of the atlas to draw the tile with, given its shore mask.
+    /// Tiles without a shore pick one of their variants from `roll`,
+    /// which should come from the tile's position so the choice never changes.
+    pub fn uv(&self, tile_type: TileType, shore_mask: u8, roll: u64) -> Rect
+    {
+        if let Some(rect) = self.shores.get(&(tile_type, shore_mask))
+        {
+            return *rect;
+        }
+        let Some(variants) = self.uvs.get(&tile_type)
+        else
+        {
+            return Rect::default();
+        };
+        let total = variants.iter().map(|variant| variant.weight as u64).sum::<u64>();
+        if total == 0
+        {
+            return variants.first().map(|variant| variant.rect).unwrap_or_default();
+        }
+        let mut roll = roll % total;
+        for variant in variants
+        {
+            if roll < variant.weight as u64
+            {
+                return variant.rect;
+            }
+            roll -= variant.weight as u64;
+        }
+        Rect::default()
+    }
+}
+
 #[derive(Resource)]
 pub struct WorldData
 {
-    pub chunks: HashMap<(i32, i32), Chunk>,
-    pub noise_map: NoiseMap,
+    pub chunks: HashMap<ChunkPos, Chunk>,
+    /// Chunks being generated on the async compute task pool.
+    pub pending: HashMap<ChunkPos, Task<Chunk>>,
+    /// Changes made to chunks that are not in `chunks`, reapplied when they are generated again.
+    pub deltas: HashMap<ChunkPos, ChunkDelta>,
+    /// Chunks whose creatures were already spawned, they don't spawn again.
+    pub spawned: HashSet<ChunkPos>,
+    /// Creatures that were standing in chunks when they unloaded, spawned back when the chunks load again.
+    pub parked: HashMap<ChunkPos, Vec<ParkedCreature>>,
+    pub generator: WorldGenerator,
+}
+
+impl WorldData
+{
+    /// Returns the type of the tile at the given position, if its chunk was generated.
+    pub fn tile_at(&self, pos: WorldPos) -> Option<TileType>
+    {
+        self.tile(pos.tile())
+    }
+
+    /// Returns the type of the tile, if its chunk was generated.
+    pub fn tile(&self, tile: TilePos) -> Option<TileType>
+    {
+        self.chunk_of(tile).map(|(chunk, (i, j))| chunk.tiles[i][j])
+    }
+
+    /// Returns the chunk holding the tile and the tile's index in it, if the chunk was generated.
+    fn chunk_of(&self, tile: TilePos) -> Option<(&Chunk, (usize, usize))>
+    {
+        self.chunks.get(&tile.chunk()).map(|chunk| (chunk, tile.local()))
+    }
+
+    /// Changes the type of the tile and remembers the change.
+    /// Returns false if the tile's chunk isn't generated.
+    pub fn set_tile(&mut self, tile: TilePos, tile_type: TileType) -> bool
+    {
+        match self.chunks.get_mut(&tile.chunk())
+        {
+            Some(chunk) =>
+            {
+                chunk.set_tile(tile.local(), tile_type);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the direction the river flows in at the position, as the offset to the tile it flows into.
+    /// `None` if the chunk isn't generated or the tile isn't running water.
+    pub fn flow_at(&self, pos: WorldPos) -> Option<IVec2>
+    {
+        self.tile_at(pos).filter(TileType::is_water)?;
+        let (chunk, (i, j)) = self.chunk_of(pos.tile())?;
+        chunk.flows[i][j]
+    }
+
+    /// Returns the kind of water at the position, if its chunk was generated and the tile is water.
+    /// Water dug out of land counts as a lake.
+    pub fn water_body_at(&self, pos: WorldPos) -> Option<WaterBody>
+    {
+        self.tile_at(pos).filter(TileType::is_water)?;
+        let (chunk, (i, j)) = self.chunk_of(pos.tile())?;
+        Some(chunk.biomes[i][j].water_body().unwrap_or(WaterBody::Lake))
+    }
+
+    /// Adds a freshly generated chunk to the world, along with the changes made to it previously.
+    pub fn insert_chunk(&mut self, chunk_pos: ChunkPos, mut chunk: Chunk)
+    {
+        if let Some(delta) = self.deltas.remove(&chunk_pos)
+        {
+            chunk.apply_delta(delta);
+        }
+        self.chunks.insert(chunk_pos, chunk);
+    }
+
+    /// Removes the chunk from the world, keeping the changes made to it.
+    pub fn remove_chunk(&mut self, chunk_pos: ChunkPos) -> Option<Chunk>
+    {
+        let chunk = self.chunks.remove(&chunk_pos)?;
+        if !chunk.delta.is_empty()
+        {
+            self.deltas.insert(chunk_pos, chunk.delta.clone());
+        }
+        Some(chunk)
+    }
+
+    /// Returns the changes made to every chunk, loaded or not.
+    pub fn all_deltas(&self) -> impl Iterator<Item = (ChunkPos, &ChunkDelta)>
+    {
+        self.deltas
+            .iter()
+            .map(|(chunk_pos, delta)| (*chunk_pos, delta))
+            .chain(self.chunks.iter().map(|(chunk_pos, chunk)| (*chunk_pos, &chunk.delta)))
+            .filter(|(_, delta)| !delta.is_empty())
+    }
+
+    /// Returns the creatures of the chunk the first time it is asked, nothing afterwards.
+    pub fn take_creatures(&mut self, chunk_pos: ChunkPos) -> Vec<CreatureSpawn>
+    {
+        match self.chunks.get_mut(&chunk_pos)
+        {
+            Some(chunk) if self.spawned.insert(chunk_pos) => std::mem::take(&mut chunk.creatures),
+            _ => vec![],
+        }
+    }
+
+    /// Keeps the creature until the chunk it stands in is loaded again.
+    pub fn park(&mut self, creature: ParkedCreature)
+    {
+        let chunk_pos = WorldPos::from(creature.translation).chunk();
+        self.parked.entry(chunk_pos).or_default().push(creature);
+    }
+
+    /// Returns true if the chunk is generated and its entities are spawned.
+    pub fn is_chunk_loaded(&self, chunk_pos: ChunkPos) -> bool
+    {
+        self.chunks
+            .get(&chunk_pos)
+            .is_some_and(|chunk| chunk.is_loaded())
+    }
+}
+
+/// A creature waiting to be spawned on the tile at `tile` inside its chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct CreatureSpawn
+{
+    pub kind: CreatureKind,
+    pub tile: (usize, usize),
+}
+
+/// A creature taken out of the world with its chunk, at the position it stood at.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ParkedCreature
+{
+    pub kind: CreatureKind,
+    pub translation: Vec3,
+    /// Health the creature had left, it comes back just as wounded.
+    pub health: u32,
+}
+
+/// A prop standing on the tile at `tile` inside its chunk.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ChunkProp
+{
+    pub kind: PropKind,
+    pub tile: (usize, usize),
+}
+
+/// Changes made to a chunk since it was generated.
+/// Generating the chunk again and applying its delta gives back the changed chunk.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChunkDelta
+{
+    /// Tiles that were replaced, by index in the chunk's tile array.
+    pub tiles: BTreeMap<(usize, usize), TileType>,
+    /// Props that were placed (`Some`) or removed (`None`), by index in the chunk's tile array.
+    pub props: BTreeMap<(usize, usize), Option<PropKind>>,
+}
+
+impl ChunkDelta
+{
+    pub fn is_empty(&self) -> bool
+    {
+        self.tiles.is_empty() && self.props.is_empty()
+    }
 }
 
 #[derive(Debug)]
 pub struct Chunk
 {
+    /// Tiles of the chunk, with the changes from `delta` already applied.
     pub tiles: [[TileType; CHUNK_SIDE as usize]; CHUNK_SIDE as usize],
+    pub biomes: [[Biome; CHUNK_SIDE as usize]; CHUNK_SIDE as usize],
+    /// Direction each river tile flows in, as the offset to the tile it flows into.
+    pub flows: [[Option<IVec2>; CHUNK_SIDE as usize]; CHUNK_SIDE as usize],
+    /// Props of the chunk, respawned every time the chunk is loaded. At most one per tile.
+    pub props: Vec<ChunkProp>,
+    /// Creatures spawned the first time the chunk is loaded.
+    pub creatures: Vec<CreatureSpawn>,
+    /// Changes made to the chunk since it was generated.
+    pub delta: ChunkDelta,
+    /// Entities spawned for the chunk while it is loaded, tiles and props alike.
+    pub entities: Vec<Entity>,
     pub is_loaded: bool,
 }
 
@@ -30,31 +384,148 @@ impl Chunk
     {
         self.is_loaded = is_loaded;
     }
+
+    /// Replaces the tile at the given index and remembers the change.
+    pub fn set_tile(&mut self, local: (usize, usize), tile_type: TileType)
+    {
+        self.tiles[local.0][local.1] = tile_type;
+        self.delta.tiles.insert(local, tile_type);
+    }
+
+    /// Returns the prop standing on the tile at the given index.
+    pub fn prop_at(&self, local: (usize, usize)) -> Option<PropKind>
+    {
+        self.props
+            .iter()
+            .find(|prop| prop.tile == local)
+            .map(|prop| prop.kind)
+    }
+
+    /// Places a prop on the tile at the given index, replacing any prop already there,
+    /// or clears the tile if `kind` is `None`. The change is remembered.
+    pub fn set_prop(&mut self, local: (usize, usize), kind: Option<PropKind>)
+    {
+        self.props.retain(|prop| prop.tile != local);
+        if let Some(kind) = kind
+        {
+            self.props.push(ChunkProp { kind, tile: local });
+        }
+        self.delta.props.insert(local, kind);
+    }
+
+    /// Writes the part of the structure that lies in this chunk over the generated tiles.
+    /// Whatever was generated on the covered tiles is cleared, only the structure's own props and creatures remain.
+    fn stamp(&mut self, template: &StructureTemplate, structure: PlacedStructure, chunk_pos: ChunkPos)
+    {
+        for i in 0..CHUNK_SIDE as usize
+        {
+            for j in 0..CHUNK_SIDE as usize
+            {
+                let Some((column, row)) = structure.local(template, chunk_pos.tile((i, j)))
+                else
+                {
+                    continue;
+                };
+                self.props.retain(|prop| prop.tile != (i, j));
+                self.creatures.retain(|creature| creature.tile != (i, j));
+                if let Some(tile_type) = template.tile(column, row)
+                {
+                    self.tiles[i][j] = tile_type;
+                    self.flows[i][j] = None;
+                }
+                if let Some((_, kind)) = template.props.iter().find(|(at, _)| *at == (column, row))
+                {
+                    self.props.push(ChunkProp { kind: *kind, tile: (i, j) });
+                }
+                for (_, kind) in template.spawns.iter().filter(|(at, _)| *at == (column, row))
+                {
+                    self.creatures.push(CreatureSpawn { kind: *kind, tile: (i, j) });
+                }
+            }
+        }
+    }
+
+    /// Replays the changes of `delta` on top of the generated chunk.
+    pub fn apply_delta(&mut self, delta: ChunkDelta)
+    {
+        for (local, tile_type) in delta.tiles.iter()
+        {
+            self.set_tile(*local, *tile_type);
+        }
+        for (local, kind) in delta.props.iter()
+        {
+            self.set_prop(*local, *kind);
+        }
+    }
 }
 
 pub trait Generate where {
-    fn generate(map: &NoiseMap, translate: Vec2) -> Self;
+    fn generate(generator: &WorldGenerator, chunk_pos: ChunkPos) -> Self;
 }
 
 impl Generate for Chunk
 {
-    fn generate(map: &NoiseMap, translate: Vec2) -> Self {
+    fn generate(generator: &WorldGenerator, chunk_pos: ChunkPos) -> Self {
         let mut tiles = [[TileType::Grass; CHUNK_SIDE as usize]; CHUNK_SIDE as usize];
-        for i in 0..CHUNK_SIDE
+        let mut biomes = [[Biome::Plains; CHUNK_SIDE as usize]; CHUNK_SIDE as usize];
+        let mut flows = [[None; CHUNK_SIDE as usize]; CHUNK_SIDE as usize];
+        let mut props = vec![];
+        let mut creatures = vec![];
+        let mut rng = generator.seed.chunk_rng(chunk_pos);
+        let river_tiles = generator.river_tiles(chunk_pos);
+        for i in 0..CHUNK_SIDE as usize
         {
-            for j in 0..CHUNK_SIDE
+            for j in 0..CHUNK_SIDE as usize
             {
-                // random gen
-                let value = map.get_value((translate.x * CHUNK_SIDE as f32 + i as f32 + MAP_SIDE as f32 * 1000.) as usize % MAP_SIDE,( translate.y  * CHUNK_SIDE as f32 + j as f32 + MAP_SIDE as f32 * 1000.) as usize % MAP_SIDE);
-                if value < 0.0
+                let tile = chunk_pos.tile((i, j));
+                let (mut biome, elevation) = generator.biome(tile);
+                // rivers only run over land, the sea and lakes they flow into stay as they are
+                if biome.water_body().is_none()
+                {
+                    match river_tiles.get(&tile)
+                    {
+                        Some(RiverTile::Flow(direction)) =>
+                        {
+                            biome = Biome::River;
+                            flows[i][j] = Some(*direction);
+                        }
+                        Some(RiverTile::Pool) => biome = Biome::Lake,
+                        None => (),
+                    }
+                }
+                tiles[i][j] = biome.tile(elevation);
+                biomes[i][j] = biome;
+
+                // keep the spawn point clear so the player doesn't start inside a tree
+                let near_spawn = tile.x.abs() <= 1 && tile.y.abs() <= 1;
+                if !near_spawn
+                {
+                    if let Some(kind) = biome::roll(biome.props(), &mut rng)
+                    {
+                        props.push(ChunkProp { kind, tile: (i, j) });
+                    }
+                }
+
+                if let Some(kind) = biome::roll(biome.creatures(), &mut rng)
                 {
-                    tiles[i as usize][j as usize] = TileType::Water;
-                } 
+                    creatures.push(CreatureSpawn { kind, tile: (i, j) });
+                }
             }
         }
-        Self {
+        let mut chunk = Self {
             tiles,
+            biomes,
+            flows,
+            props,
+            creatures,
+            delta: ChunkDelta::default(),
+            entities: vec![],
             is_loaded: false,
+        };
+        for structure in generator.structures_near(chunk_pos)
+        {
+            chunk.stamp(generator.template(&structure), structure, chunk_pos);
         }
+        chunk
     }
 }
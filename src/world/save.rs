@@ -0,0 +1,151 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use bevy::utils::HashMap;
+
+use super::coords::ChunkPos;
+use super::generation::WorldGenerator;
+use super::resources::{ChunkDelta, ParkedCreature, WorldData};
+
+/// File the world is saved to when the `save` setting is not set.
+pub const DEFAULT_SAVE_FILE: &str = "save.ron";
+
+/// Everything needed to rebuild a world: the seed it was generated from and the changes made to it.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SaveData
+{
+    pub seed: u32,
+    pub chunks: Vec<(ChunkPos, ChunkDelta)>,
+    /// Chunks whose creatures were already spawned.
+    #[serde(default)]
+    pub spawned: Vec<ChunkPos>,
+    /// Creatures of the spawned chunks, alive or parked, spawned back when their chunk loads.
+    #[serde(default)]
+    pub creatures: Vec<ParkedCreature>,
+}
+
+impl SaveData
+{
+    /// Collects the seed, every changed chunk of the world and its creatures, the parked ones and those `alive`.
+    pub fn from_world(world_data: &WorldData, alive: impl IntoIterator<Item = ParkedCreature>) -> Self
+    {
+        Self {
+            seed: world_data.generator.seed.0,
+            chunks: world_data
+                .all_deltas()
+                .map(|(chunk_pos, delta)| (chunk_pos, delta.clone()))
+                .collect(),
+            spawned: world_data.spawned.iter().copied().collect(),
+            creatures: world_data.parked.values().flatten().copied().chain(alive).collect(),
+        }
+    }
+
+    /// Builds the world back, every saved creature parked until its chunk loads.
+    pub fn into_world(self, generator: WorldGenerator) -> WorldData
+    {
+        let mut world_data = WorldData {
+            chunks: HashMap::default(),
+            pending: HashMap::default(),
+            deltas: self.chunks.into_iter().collect(),
+            spawned: self.spawned.into_iter().collect(),
+            parked: HashMap::default(),
+            generator,
+        };
+        for creature in self.creatures
+        {
+            world_data.park(creature);
+        }
+        world_data
+    }
+
+    /// Reads the save file, returns `None` if there is none or it can't be read.
+    pub fn load(path: &str) -> Option<Self>
+    {
+        let contents = fs::read_to_string(path).ok()?;
+        match ron::from_str(&contents)
+        {
+            Ok(save) => Some(save),
+            Err(error) =>
+            {
+                warn!("Could not read save file {}: {}", path, error);
+                None
+            }
+        }
+    }
+
+    pub fn write(&self, path: &str)
+    {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| fs::write(path, contents).map_err(|error| error.to_string()));
+        match result
+        {
+            Ok(()) => info!("World saved to {}", path),
+            Err(error) => warn!("Could not write save file {}: {}", path, error),
+        }
+    }
+}
+
+/// Returns the path of the save file, from the `save` setting.
+pub fn save_path() -> String
+{
+    crate::config::get("save").unwrap_or_else(|| DEFAULT_SAVE_FILE.to_string())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use bevy::prelude::Vec3;
+
+    use super::*;
+    use crate::world::biome::CreatureKind;
+    use crate::world::coords::WorldPos;
+    use crate::world::resources::WorldSeed;
+
+    fn fish_man(x: f32, z: f32, health: u32) -> ParkedCreature
+    {
+        ParkedCreature {
+            kind: CreatureKind::FishMan,
+            translation: Vec3::new(x, 1., z),
+            health,
+        }
+    }
+
+    #[test]
+    fn creatures_survive_a_save()
+    {
+        let generator = WorldGenerator::new(WorldSeed(7), vec![]);
+        let mut world_data = SaveData::default().into_world(generator.clone());
+        let explored = [ChunkPos::new(0, 0), ChunkPos::new(-1, 2)];
+        world_data.spawned.extend(explored);
+        let parked = fish_man(-12., 20., 40);
+        world_data.park(parked);
+        let alive = fish_man(3., -1., 75);
+
+        let contents = ron::to_string(&SaveData::from_world(&world_data, [alive])).unwrap();
+        let save = ron::from_str::<SaveData>(&contents).unwrap();
+        assert_eq!(save.seed, 7);
+        let mut loaded = save.into_world(generator);
+
+        for chunk_pos in explored
+        {
+            assert!(loaded.spawned.contains(&chunk_pos));
+        }
+        for creature in [parked, alive]
+        {
+            let chunk_pos = WorldPos::from(creature.translation).chunk();
+            assert_eq!(loaded.parked.remove(&chunk_pos), Some(vec![creature]));
+        }
+        assert!(loaded.parked.is_empty());
+    }
+
+    #[test]
+    fn old_saves_still_load()
+    {
+        let save = ron::from_str::<SaveData>("(seed: 3, chunks: [])").unwrap();
+        assert!(save.spawned.is_empty());
+        assert!(save.creatures.is_empty());
+    }
+}
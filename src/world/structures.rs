@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use super::biome::{Biome, CreatureKind, PropKind};
+use super::components::TileType;
+use super::coords::{ChunkPos, TilePos};
+use super::generation::WorldGenerator;
+use super::CHUNK_SIDE;
+
+/// Folder the structure templates are read from, one `.ron` file per template.
+/// Relative to the folder Bevy looks for the `assets` folder in.
+pub const STRUCTURES_DIR: &str = "assets/structures";
+/// Largest size of a structure, in chunks, so only the nearby chunks have to be checked for them.
+pub const MAX_CHUNK_SPAN: usize = 2;
+
+/// A multi-tile structure that can be stamped into the world, read from a data file.
+///
+/// `layout` is a grid of characters, one string per row going south, one character per tile going east.
+/// Characters found in `legend` replace the tile, any other character leaves the generated tile alone.
+#[derive(Deserialize, Debug, Clone)]
+pub struct StructureTemplate
+{
+    pub name: String,
+    /// Probability of the structure being placed in any given chunk.
+    pub chance: f64,
+    /// Biomes the structure can be placed in, checked at its first tile. Empty means any biome.
+    #[serde(default)]
+    pub biomes: Vec<Biome>,
+    pub layout: Vec<String>,
+    #[serde(default)]
+    pub legend: HashMap<char, TileType>,
+    /// Props placed on the structure, by `(column, row)` in the layout.
+    #[serde(default)]
+    pub props: Vec<((usize, usize), PropKind)>,
+    /// Creatures spawned on the structure the first time it is loaded, by `(column, row)` in the layout.
+    #[serde(default)]
+    pub spawns: Vec<((usize, usize), CreatureKind)>,
+}
+
+/// A structure placed in the world, `origin` is the tile of its top left corner.
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedStructure
+{
+    pub template: usize,
+    pub origin: TilePos,
+}
+
+impl StructureTemplate
+{
+    /// Size of the layout, in tiles, as `(columns, rows)`.
+    pub fn size(&self) -> (usize, usize)
+    {
+        let columns = self.layout.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        (columns, self.layout.len())
+    }
+
+    /// Returns the tile the layout puts at `(column, row)`, if it replaces the generated one.
+    pub fn tile(&self, column: usize, row: usize) -> Option<TileType>
+    {
+        let character = self.layout.get(row)?.chars().nth(column)?;
+        self.legend.get(&character).copied()
+    }
+}
+
+impl PlacedStructure
+{
+    /// Returns the position in the structure's layout of the tile, if the structure covers it.
+    pub fn local(&self, template: &StructureTemplate, tile: TilePos) -> Option<(usize, usize)>
+    {
+        let (columns, rows) = template.size();
+        let (column, row) = (tile.x - self.origin.x, tile.y - self.origin.y);
+        if column < 0 || row < 0 || column as usize >= columns || row as usize >= rows
+        {
+            return None;
+        }
+        Some((column as usize, row as usize))
+    }
+}
+
+/// Returns the folder the structure templates are read from.
+/// Found the same way Bevy finds the assets, so it doesn't depend on the working directory.
+pub fn structures_dir() -> PathBuf
+{
+    FileAssetReader::get_base_path().join(STRUCTURES_DIR)
+}
+
+/// Reads every structure template in `dir`. Templates that can't be read are skipped with a warning.
+pub fn load_templates(dir: &Path) -> Vec<StructureTemplate>
+{
+    let Ok(entries) = fs::read_dir(dir)
+    else
+    {
+        warn!("Could not read structure folder {}, no structures will be placed", dir.display());
+        return vec![];
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .collect::<Vec<_>>();
+    // the order decides which template wins a chunk, it must not depend on the file system
+    paths.sort();
+
+    let mut templates = vec![];
+    for path in paths
+    {
+        let template = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                ron::from_str::<StructureTemplate>(&contents).map_err(|error| error.to_string())
+            });
+        match template
+        {
+            Ok(template) if template.size().0 > CHUNK_SIDE as usize * MAX_CHUNK_SPAN
+                || template.size().1 > CHUNK_SIDE as usize * MAX_CHUNK_SPAN =>
+            {
+                warn!("Structure {} is too big, it is skipped", template.name);
+            }
+            Ok(template) => templates.push(template),
+            Err(error) => warn!("Could not read structure {}: {}", path.display(), error),
+        }
+    }
+    templates
+}
+
+impl WorldGenerator
+{
+    /// Returns the structure whose origin lies in the chunk, if the chunk gets one.
+    /// Only depends on the seed and the chunk's position, so every chunk agrees on where structures are.
+    pub fn structure_in(&self, chunk_pos: ChunkPos) -> Option<PlacedStructure>
+    {
+        let mut rng = self.seed.structure_rng(chunk_pos);
+        // one roll per template, in the sorted order of their files. A template whose file sorts last
+        // leaves the rolls of the others alone, any other new template shifts the rolls after it.
+        let rolls = self
+            .structures
+            .iter()
+            .map(|template| rng.gen_bool(template.chance.clamp(0.0, 1.0)))
+            .collect::<Vec<_>>();
+        let template = rolls.iter().position(|placed| *placed)?;
+        // the origin has its own rng, so it stays put whatever templates there are
+        let mut origin_rng = self.seed.structure_origin_rng(chunk_pos);
+        let origin = chunk_pos.tile((
+            origin_rng.gen_range(0..CHUNK_SIDE as usize),
+            origin_rng.gen_range(0..CHUNK_SIDE as usize),
+        ));
+
+        let structure = &self.structures[template];
+        if !structure.biomes.is_empty() && !structure.biomes.contains(&self.biome(origin).0)
+        {
+            return None;
+        }
+        // the player must not start inside a structure
+        let placed = PlacedStructure { template, origin };
+        let covers_spawn = (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |y| TilePos::new(x, y)))
+            .any(|tile| placed.local(structure, tile).is_some());
+        if covers_spawn
+        {
+            return None;
+        }
+        Some(placed)
+    }
+
+    /// Returns every structure that may cover a tile of the chunk.
+    pub fn structures_near(&self, chunk_pos: ChunkPos) -> Vec<PlacedStructure>
+    {
+        let span = MAX_CHUNK_SPAN as i32;
+        // structures grow east and south from their origin, so only look west and north
+        (-span..=0)
+            .flat_map(|x| (-span..=0).map(move |y| chunk_pos.offset(x, y)))
+            .filter_map(|other| self.structure_in(other))
+            .collect()
+    }
+
+    pub fn template(&self, structure: &PlacedStructure) -> &StructureTemplate
+    {
+        &self.structures[structure.template]
+    }
+}
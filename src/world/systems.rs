@@ -1,21 +1,24 @@
+use bevy::app::AppExit;
 use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy::tasks::{block_on, AsyncComputeTaskPool};
 use bevy_sprite3d::*;
 use rand::distributions::{Bernoulli, Distribution};
 use rand::rngs::ThreadRng;
 
 
 use crate::assets::MyAssets;
-use crate::entities::collider::Collider;
+use crate::entities::collider::{Collider, Layers};
 use crate::entities::player::components::Player;
-use std::f32::consts::FRAC_PI_2;
+use crate::entities::shape::Shape;
+use crate::entities::unit::Health;
 
-use super::{components::*, resources::*, CHUNK_RADIUS, CHUNK_SIDE};
-
-#[derive(Bundle)]
-struct TileBundle {
-    tile_type: TileType,
-    sprite: Sprite3dBundle,
-}
+use super::autotile::{shore_image, shore_masks};
+use super::biome::PropKind;
+use super::coords::{ChunkPos, TilePos, WorldPos, TILE_SIZE};
+use super::mesh::{build_chunk_mesh, water_rects, TileRect};
+use super::save::{save_path, SaveData};
+use super::{components::*, events::*, resources::*};
 
 #[derive(Bundle)]
 struct SolidObjectBundle {
@@ -53,29 +56,68 @@ struct SolidObjectBundle {
 //     }
 // }
 
-/// Spawns a rock at the given position.
-fn spawn_rock(i: i32, j: i32, common: &mut (&mut Commands, &MyAssets, &mut Sprite3dParams)) {
-    common.0.spawn(SolidObjectBundle {
-        collider: Collider {
-            size: Vec2::new(1.8, 0.5),
-            active: true,
-        },
-        sprite: Sprite3d {
-            image: common.1.rock.clone(),
-            pixels_per_metre: 16.0,
-            unlit: true,
-            transform: Transform::from_xyz((i as f32) * 2., 1., (j as f32) * 2. + 1.),
-            ..default()
-        }
-        .bundle(&mut common.2),
-    });
+/// Spawns a prop at the given position.
+fn spawn_prop(
+    kind: PropKind,
+    tile: TilePos,
+    common: &mut (&mut Commands, &MyAssets, &mut Sprite3dParams),
+) -> Entity {
+    common
+        .0
+        .spawn((
+            Prop,
+            SolidObjectBundle {
+                collider: Collider::solid(get_prop_collider_shape(kind), Layers::SOLID),
+                sprite: Sprite3d {
+                    image: get_prop_image(common.1, kind),
+                    pixels_per_metre: 16.0,
+                    unlit: true,
+                    transform: Transform::from_translation(tile.center().with_height(1.)),
+                    ..default()
+                }
+                .bundle(&mut common.2),
+            },
+        ))
+        .id()
+}
+
+/// Returns the image for the given prop.
+fn get_prop_image(assets: &MyAssets, kind: PropKind) -> Handle<Image> {
+    match kind {
+        PropKind::Rock => assets.rock.clone(),
+        PropKind::Tree => assets.tree.clone(),
+        PropKind::Sapling => assets.sapling.clone(),
+    }
+}
+
+/// Returns the shape of the collider for the given prop, only the base of the sprite blocks.
+fn get_prop_collider_shape(kind: PropKind) -> Shape {
+    match kind {
+        PropKind::Rock => Shape::capsule_x(1.8, 0.25),
+        PropKind::Tree => Shape::Circle(0.25),
+        PropKind::Sapling => Shape::Circle(0.12),
+    }
 }
 
 /// Returns the image for the given tile type.
 fn get_tile_image(assets: &MyAssets, tile_type: TileType) -> Handle<Image> {
     match tile_type {
         TileType::Grass => assets.grass.clone(),
-        TileType::Water => assets.water.clone(),
+        TileType::ShallowWater => assets.water.clone(),
+        TileType::DeepWater => assets.deep_water.clone(),
+        TileType::Sand => assets.sand.clone(),
+        TileType::ForestFloor => assets.forest_floor.clone(),
+        TileType::Rock => assets.stone.clone(),
+        TileType::Dirt => assets.dirt.clone(),
+        TileType::Bridge => assets.bridge.clone(),
+    }
+}
+
+/// Returns every image the tile type can be drawn with, along with how often it is picked.
+fn get_tile_variants(assets: &MyAssets, tile_type: TileType) -> Vec<(Handle<Image>, u32)> {
+    match tile_type {
+        TileType::Grass => vec![(assets.grass.clone(), 3), (assets.grass_var2.clone(), 1)],
+        _ => vec![(get_tile_image(assets, tile_type), 1)],
     }
 }
 
@@ -93,237 +135,470 @@ fn probability(odds: f64, rng: &mut ThreadRng) -> bool {
 pub fn spawn_tiles_around_player(
     mut commands: Commands,
     assets: Res<MyAssets>,
+    atlas: Res<TileAtlas>,
     mut sprite3d_params: Sprite3dParams,
     player: Query<&Transform, With<Player>>,
     mut world_data: ResMut<WorldData>,
+    settings: Res<WorldSettings>,
+    mut creature_events: EventWriter<SpawnCreature>,
 ) {
-    // Add a chunk to the world around the player if it doesn't exist
-    let player_pos = player.single().translation;
-    let player_pos = Vec2::new(player_pos.x, player_pos.z);
-
-    let chunk_coords = get_chunk_pos(player_pos);
+    // Load every chunk around the player at once, the player must not start in the void
+    let chunk_coords = WorldPos::from(player.single().translation).chunk();
 
-    for i in -1..=1 {
-        for j in -1..=1 {
-            add_chunk(&mut world_data, chunk_coords, i, j);
+    let chunks = chunks_around(chunk_coords, settings.load_radius);
 
-            let (a, b) = (chunk_coords.0 + i, chunk_coords.1 + j);
-
-            let chunk = world_data.chunks.get_mut(&(a, b));
+    // generate them all first, so the shores along their borders are known when they spawn
+    for chunk_pos in chunks.iter() {
+        add_chunk(&mut world_data, *chunk_pos);
+    }
+    for chunk_pos in chunks {
+        load_chunk(
+            &mut commands,
+            &assets,
+            &atlas,
+            &mut sprite3d_params,
+            &mut creature_events,
+            &mut world_data,
+            chunk_pos,
+        );
+    }
+}
 
-            match chunk {
-                Some(chunk) => {
-                    spawn_chunk(
-                        &mut commands,
-                        &assets,
-                        &mut sprite3d_params,
-                        chunk,
-                        (a * CHUNK_SIDE) as i32,
-                        (b * CHUNK_SIDE) as i32,
-                    );
-                    chunk.set_is_loaded(true);
-                }
-                None => (),
-            }
+/// Returns the chunks at most `radius` chunks away from `center`, closest first.
+fn chunks_around(center: ChunkPos, radius: i32) -> Vec<ChunkPos> {
+    let radius = radius.max(0);
+    let mut chunks = Vec::with_capacity(((radius * 2 + 1) * (radius * 2 + 1)) as usize);
+    for i in -radius..=radius {
+        for j in -radius..=radius {
+            chunks.push(center.offset(i, j));
         }
     }
+    // closest first, so the area around the player fills in before the edges
+    chunks.sort_by_key(|pos| (pos.x - center.x).pow(2) + (pos.y - center.y).pow(2));
+    chunks
 }
 
-fn add_chunk(world_data: &mut ResMut<'_, WorldData>, chunk_coords: (i32, i32), i: i32, j: i32) {
-    let chunk = world_data
-        .chunks
-        .get(&(chunk_coords.0 + i, chunk_coords.1 + j));
+/// Generates the chunk right away if it doesn't exist yet.
+fn add_chunk(world_data: &mut ResMut<'_, WorldData>, chunk_pos: ChunkPos) {
+    let chunk = world_data.chunks.get(&chunk_pos);
 
     match chunk {
         Some(_) => (),
         None => {
-
-            let chunk = Chunk::generate(&(world_data.noise_map), Vec2::new((chunk_coords.0 + i) as f32, (chunk_coords.1 + j) as f32));
-            world_data
-                .chunks
-                .insert((chunk_coords.0 + i, chunk_coords.1 + j), chunk);
+            let chunk = match world_data.pending.remove(&chunk_pos) {
+                Some(task) => block_on(task),
+                None => Chunk::generate(&world_data.generator, chunk_pos),
+            };
+            world_data.insert_chunk(chunk_pos, chunk);
         }
     }
 }
 
-pub fn get_chunk_pos(player_pos: Vec2) -> (i32, i32) {
-    let mut x = player_pos.x as i32;
-    let mut y = player_pos.y as i32;
-
-    if x > 0 {
-        x = (x + CHUNK_SIDE) / (CHUNK_SIDE * 2);
-    } else {
-        x = (x - CHUNK_SIDE) / (CHUNK_SIDE * 2);
-    }
-    if y > 0 {
-        y = (y + CHUNK_SIDE) / (CHUNK_SIDE * 2);
-    } else {
-        y = (y - CHUNK_SIDE) / (CHUNK_SIDE * 2);
+/// Starts generating the chunk on the async compute task pool,
+/// unless it is already generated or being generated.
+fn request_chunk(world_data: &mut ResMut<'_, WorldData>, chunk_pos: ChunkPos) {
+    if world_data.chunks.contains_key(&chunk_pos) || world_data.pending.contains_key(&chunk_pos) {
+        return;
     }
+    let generator = world_data.generator.clone();
+    let task = AsyncComputeTaskPool::get()
+        .spawn(async move { Chunk::generate(&generator, chunk_pos) });
+    world_data.pending.insert(chunk_pos, task);
+}
 
-    let chunk_coords = (x, y);
-    chunk_coords
+/// Moves the chunks that finished generating from the pending list into the world.
+pub fn receive_generated_chunks(mut world_data: ResMut<WorldData>) {
+    let finished = world_data
+        .pending
+        .iter()
+        .filter(|(_, task)| task.is_finished())
+        .map(|(chunk_pos, _)| *chunk_pos)
+        .collect::<Vec<_>>();
+
+    for chunk_pos in finished {
+        if let Some(task) = world_data.pending.remove(&chunk_pos) {
+            let chunk = block_on(task);
+            world_data.insert_chunk(chunk_pos, chunk);
+        }
+    }
 }
 
 /// Spawns a chunk at the given position.
-/// The chunk is spawned at the given position, with the center of the chunk at the given position.
+/// The tiles are drawn by a single mesh, water is covered by as few colliders as possible.
+/// Returns every entity spawned for the chunk, so they can be despawned when it unloads.
 fn spawn_chunk(
     commands: &mut Commands,
     assets: &MyAssets,
+    atlas: &TileAtlas,
     sprite3d_params: &mut Sprite3dParams,
-    chunk: &Chunk,
-    i: i32,
-    j: i32,
-) {
-    let start_x = i - CHUNK_SIDE / 2;
-    let start_y = j - CHUNK_SIDE / 2;
-
-    for (x, row) in chunk.tiles.iter().enumerate() {
-        for (y, tile) in row.iter().enumerate() {
-            let tile_bundle = TileBundle::new(
-                *tile,
-                start_x + x as i32,
-                start_y + y as i32,
-                assets,
-                sprite3d_params,
-            );
+    world_data: &WorldData,
+    chunk_pos: ChunkPos,
+) -> Vec<Entity> {
+    let Some(chunk) = world_data.chunks.get(&chunk_pos) else {
+        return vec![];
+    };
+    let mut common = (commands, assets, sprite3d_params);
+    let mut entities = vec![];
+
+    let mesh = common
+        .2
+        .meshes
+        .add(build_chunk_mesh(
+            chunk,
+            chunk_pos,
+            atlas,
+            &world_data.generator.seed,
+            |tile| world_data.tile(tile),
+        ));
+    entities.push(
+        common
+            .0
+            .spawn((
+                ChunkMesh(chunk_pos),
+                PbrBundle {
+                    mesh,
+                    material: atlas.material.clone(),
+                    transform: Transform::from_translation(
+                        chunk_pos.center().center().with_height(0.),
+                    ),
+                    ..default()
+                },
+            ))
+            .id(),
+    );
 
-            match *tile {
-                TileType::Grass => commands.spawn(tile_bundle),
-                TileType::Water => commands.spawn((
-                    tile_bundle,
-                    Collider {
-                        size: Vec2::new(2., 2.),
-                        active: true,
-                    },
-                )),
-            };
-        }
+    for rect in water_rects(chunk) {
+        entities.push(spawn_water_collider(common.0, chunk_pos, rect));
+    }
+
+    for prop in chunk.props.iter() {
+        entities.push(spawn_prop(prop.kind, chunk_pos.tile(prop.tile), &mut common));
     }
+
+    entities
 }
 
-impl TileBundle {
-    fn new(
-        tile_type: TileType,
-        i: i32,
-        j: i32,
-        assets: &MyAssets,
-        mut sprite3d_params: &mut Sprite3dParams,
-    ) -> Self {
-        let t = TileBundle {
-            tile_type,
-            sprite: Sprite3d {
-                image: get_tile_image(&assets, tile_type),
-                pixels_per_metre: 16.0,
-                unlit: true,
-                transform: Transform::from_xyz((i as f32) * 2., 0., (j as f32) * 2.)
-                    .with_rotation(Quat::from_rotation_x(-FRAC_PI_2)),
-                ..default()
+/// Spawns a collider covering the given rectangle of water tiles.
+fn spawn_water_collider(commands: &mut Commands, chunk_pos: ChunkPos, rect: TileRect) -> Entity {
+    let first = chunk_pos.tile(rect.start).center();
+    let size = Vec2::new(rect.size.0 as f32, rect.size.1 as f32) * TILE_SIZE;
+    // the rectangle starts at the center of its first tile
+    let center = WorldPos::new(
+        first.x + (size.x - TILE_SIZE) / 2.,
+        first.z + (size.y - TILE_SIZE) / 2.,
+    );
+
+    commands
+        .spawn((
+            WaterCollider(chunk_pos),
+            Collider::solid(Shape::Box(size), Layers::WATER),
+            TransformBundle::from_transform(Transform::from_translation(center.with_height(0.))),
+        ))
+        .id()
+}
+
+/// Packs the tile textures into a single image, used by every chunk mesh.
+/// Water tiles also get a variant for every shape of shore they can have.
+pub fn build_tile_atlas(
+    mut commands: Commands,
+    assets: Res<MyAssets>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // draw the shore variants first, the builder borrows the images until it is done
+    let mut shore_handles = vec![];
+    if let Some(sand) = images.get(&assets.sand).cloned() {
+        for tile_type in TileType::ALL.into_iter().filter(|tile| tile.is_water()) {
+            let Some(water) = images.get(&get_tile_image(&assets, tile_type)).cloned() else {
+                continue;
+            };
+            for mask in shore_masks() {
+                if let Some(image) = shore_image(&water, &sand, mask) {
+                    shore_handles.push(((tile_type, mask), images.add(image)));
+                }
             }
-            .bundle(&mut sprite3d_params),
-        };
-        return t;
+        }
+    }
+
+    let mut builder = TextureAtlasBuilder::default().max_size(Vec2::splat(4096.));
+    let tile_variants = TileType::ALL.map(|tile_type| get_tile_variants(&assets, tile_type));
+    let tile_handles = tile_variants.iter().flatten().map(|(handle, _)| handle);
+    for handle in tile_handles.chain(shore_handles.iter().map(|(_, handle)| handle)) {
+        if let Some(image) = images.get(handle) {
+            builder.add_texture(handle.id(), image);
+        }
+    }
+    let atlas = builder
+        .finish(&mut images)
+        .expect("tile textures should fit in the tile atlas");
+
+    let uv = |handle: &Handle<Image>| {
+        atlas.get_texture_index(handle.id()).map(|index| {
+            // shrink the rect a little so neighbouring textures never bleed in
+            let rect = atlas.textures[index].inset(-0.05);
+            Rect::from_corners(rect.min / atlas.size, rect.max / atlas.size)
+        })
+    };
+    let mut uvs = HashMap::default();
+    for (tile_type, variants) in TileType::ALL.into_iter().zip(tile_variants.iter()) {
+        let variants = variants
+            .iter()
+            .filter_map(|(handle, weight)| {
+                uv(handle).map(|rect| TileVariant {
+                    rect,
+                    weight: *weight,
+                })
+            })
+            .collect();
+        uvs.insert(tile_type, variants);
     }
+    let mut shores = HashMap::default();
+    for (key, handle) in shore_handles.iter() {
+        if let Some(rect) = uv(handle) {
+            shores.insert(*key, rect);
+        }
+    }
+    // the variants live in the atlas now
+    for (_, handle) in shore_handles {
+        images.remove(handle);
+    }
+
+    commands.insert_resource(TileAtlas {
+        material: materials.add(StandardMaterial {
+            base_color_texture: Some(atlas.texture),
+            unlit: true,
+            ..default()
+        }),
+        uvs,
+        shores,
+    });
 }
 
 /// Updates the tiles in the based on the player's position.
-/// Chunks are added and removed as needed.
-/// If a chunk is too far away from the player, it is removed.
+/// Missing chunks within the load radius are generated in the background,
+/// generated ones are spawned closest first, at most `max_chunks_per_frame` per frame.
 pub fn update_tiles(
     mut commands: Commands,
     assets: Res<MyAssets>,
+    atlas: Res<TileAtlas>,
     mut sprite3d_params: Sprite3dParams,
     player: Query<&Transform, With<Player>>,
     mut world_data: ResMut<WorldData>,
+    settings: Res<WorldSettings>,
+    mut creature_events: EventWriter<SpawnCreature>,
 ) {
-    // Add a chunk to the world around the player if it doesn't exist
-    let player_pos = player.single().translation;
-    let player_pos = Vec2::new(player_pos.x, player_pos.z);
-
-    let chunk_coords = get_chunk_pos(player_pos);
+    let chunk_coords = WorldPos::from(player.single().translation).chunk();
+    let mut budget = settings.max_chunks_per_frame;
 
-    for i in -1..=1 {
-        for j in -1..=1 {
-            // Add new chunks to the world
-            add_chunk(&mut world_data, chunk_coords, i, j);
+    for chunk_pos in chunks_around(chunk_coords, settings.load_radius) {
+        match world_data.chunks.get(&chunk_pos).map(Chunk::is_loaded) {
+            Some(true) => (),
             // Spawn the chunk if it not already loaded
-
-            let (a, b) = (chunk_coords.0 + i, chunk_coords.1 + j);
-
-            let chunk = world_data.chunks.get_mut(&(a, b));
-
-            match chunk {
-                Some(chunk) => {
-                    if !chunk.is_loaded() {
-                        load_chunk(&mut commands, &assets, &mut sprite3d_params, chunk, a, b);
-                    }
+            Some(false) => {
+                if budget > 0 {
+                    budget -= 1;
+                    load_chunk(
+                        &mut commands,
+                        &assets,
+                        &atlas,
+                        &mut sprite3d_params,
+                        &mut creature_events,
+                        &mut world_data,
+                        chunk_pos,
+                    );
                 }
-                None => (),
             }
+            // Add new chunks to the world
+            None => request_chunk(&mut world_data, chunk_pos),
         }
     }
 }
 
+/// Removes the chunks that are further than the unload radius from the player.
+/// Only the changes made to them are kept, the rest is generated again when they come back in range.
+/// Creatures left standing outside the loaded chunks are parked until their chunk loads again.
 pub fn deload_chunks(
     mut commands: Commands,
     player: Query<&Transform, With<Player>>,
+    creatures: Query<(Entity, &Transform, &Creature, &Health)>,
     mut world_data: ResMut<WorldData>,
-    mut entities: Query<(Entity, &Transform), With<TileType>>,
+    settings: Res<WorldSettings>,
 ) {
-    let player_pos = player.single().translation;
-    let player_pos = Vec2::new(player_pos.x, player_pos.z);
-
-    let chunk_coords = get_chunk_pos(player_pos);
+    let chunk_coords = WorldPos::from(player.single().translation).chunk();
+    let unload_radius = settings.unload_radius();
 
     // Remove chunks that are too far away from the player
-
-    for (chunk_pos, chunk) in world_data.chunks.iter_mut() {
-        if ((chunk_pos.0 - chunk_coords.0).abs() > 2 || (chunk_pos.1 - chunk_coords.1).abs() > 2)
-            && chunk.is_loaded()
-        {
-            // Remove the chunk
-            let tiles = get_chunk_tiles(*chunk_pos, &mut entities);
-            for tile in tiles {
-                commands.entity(tile).despawn();
+    let far_chunks = world_data
+        .chunks
+        .keys()
+        .filter(|chunk_pos| chunk_pos.distance(chunk_coords) > unload_radius)
+        .copied()
+        .collect::<Vec<_>>();
+
+    for chunk_pos in far_chunks {
+        if let Some(chunk) = world_data.remove_chunk(chunk_pos) {
+            for entity in chunk.entities {
+                // the entity may already be gone, e.g. a prop that got destroyed
+                if let Some(entity) = commands.get_entity(entity) {
+                    entity.despawn_recursive();
+                }
             }
-            chunk.set_is_loaded(false);
         }
     }
+
+    // creatures roam out of their chunk, so they are parked in the one they stand in
+    for (entity, transform, creature, health) in &creatures {
+        let chunk_pos = WorldPos::from(transform.translation).chunk();
+        if world_data.is_chunk_loaded(chunk_pos) {
+            continue;
+        }
+        world_data.park(ParkedCreature {
+            kind: creature.0,
+            translation: transform.translation,
+            health: health.current,
+        });
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
-fn load_chunk(
-    commands: &mut Commands<'_, '_>,
-    assets: &Res<'_, MyAssets>,
-    sprite3d_params: &mut Sprite3dParams<'_, '_>,
-    chunk: &mut Chunk,
-    a: i32,
-    b: i32,
+/// Saves the changes made to the world when F5 is pressed or the game closes.
+/// The creatures alive are saved along with the parked ones, they come back when their chunk loads.
+pub fn save_world(
+    input: Res<Input<KeyCode>>,
+    mut exit: EventReader<AppExit>,
+    creatures: Query<(&Transform, &Creature, &Health)>,
+    world_data: Res<WorldData>,
 ) {
-    spawn_chunk(
-        commands,
-        assets,
-        sprite3d_params,
-        chunk,
-        (a * CHUNK_SIDE) as i32,
-        (b * CHUNK_SIDE) as i32,
-    );
-    chunk.set_is_loaded(true);
+    let exiting = exit.read().count() > 0;
+    if input.just_pressed(KeyCode::F5) || exiting {
+        let alive = creatures.iter().map(|(transform, creature, health)| ParkedCreature {
+            kind: creature.0,
+            translation: transform.translation,
+            health: health.current,
+        });
+        SaveData::from_world(&world_data, alive).write(&save_path());
+    }
 }
 
-fn get_chunk_tiles(
-    chunk_pos: (i32, i32),
-    mut entities: &mut Query<(Entity, &Transform), With<TileType>>,
-) -> Vec<Entity> {
-    let mut tiles: Vec<Entity> = vec![];
+/// Applies the terrain edits asked for this frame.
+/// The water colliders of the edited chunk are rebuilt right away, the meshes around the tile are refreshed.
+pub fn apply_terrain_edits(
+    mut commands: Commands,
+    mut edit_events: EventReader<EditTerrain>,
+    mut refresh_events: EventWriter<RefreshChunk>,
+    mut world_data: ResMut<WorldData>,
+    water_colliders: Query<(Entity, &WaterCollider)>,
+) {
+    for EditTerrain { tile, edit } in edit_events.read() {
+        let chunk_pos = tile.chunk();
+        let Some(chunk) = world_data.chunks.get(&chunk_pos) else {
+            continue;
+        };
+        let current = chunk.tiles[tile.local().0][tile.local().1];
+        let edited = match edit {
+            // props have to be removed before digging under them
+            TerrainEdit::Dig if !current.is_water() && chunk.prop_at(tile.local()).is_none() => {
+                TileType::ShallowWater
+            }
+            TerrainEdit::Fill if current.is_water() => TileType::Dirt,
+            TerrainEdit::Bridge if current.is_water() => TileType::Bridge,
+            _ => continue,
+        };
+        world_data.set_tile(*tile, edited);
+
+        if let Some(chunk) = world_data.chunks.get_mut(&chunk_pos) {
+            if chunk.is_loaded() {
+                let old = water_colliders
+                    .iter()
+                    .filter(|(_, WaterCollider(pos))| *pos == chunk_pos)
+                    .map(|(entity, _)| entity)
+                    .collect::<Vec<_>>();
+                for entity in old.iter() {
+                    commands.entity(*entity).despawn_recursive();
+                }
+                chunk.entities.retain(|entity| !old.contains(entity));
+                for rect in water_rects(chunk) {
+                    chunk.entities.push(spawn_water_collider(&mut commands, chunk_pos, rect));
+                }
+            }
+        }
+
+        // shores reach into the neighbouring tiles, which may be in other chunks
+        let mut dirty = HashSet::new();
+        for (i, j) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 0), (0, 1), (1, -1), (1, 0), (1, 1)] {
+            dirty.insert(tile.offset(i, j).chunk());
+        }
+        refresh_events.send_batch(dirty.into_iter().map(RefreshChunk));
+    }
+}
 
-    for (entity, transform) in entities.iter_mut() {
-        let pos = Vec2::new(transform.translation.x, transform.translation.z);
-        let chunk = get_chunk_pos(pos);
+/// Rebuilds the meshes of chunks whose tiles, or whose neighbours' tiles, changed.
+/// A chunk spawning next to loaded chunks can give them new shores along their shared border.
+pub fn refresh_chunk_meshes(
+    mut refresh_events: EventReader<RefreshChunk>,
+    spawned: Query<&ChunkMesh, Added<ChunkMesh>>,
+    chunk_meshes: Query<(&ChunkMesh, &Handle<Mesh>)>,
+    world_data: Res<WorldData>,
+    atlas: Res<TileAtlas>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let mut dirty = refresh_events
+        .read()
+        .map(|RefreshChunk(chunk_pos)| *chunk_pos)
+        .collect::<HashSet<_>>();
+    for ChunkMesh(chunk_pos) in spawned.iter() {
+        for (i, j) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)] {
+            dirty.insert(chunk_pos.offset(i, j));
+        }
+    }
+    if dirty.is_empty() {
+        return;
+    }
 
-        if chunk == chunk_pos {
-            tiles.push(entity);
+    for (ChunkMesh(chunk_pos), handle) in chunk_meshes.iter() {
+        if !dirty.contains(chunk_pos) {
+            continue;
+        }
+        if let (Some(chunk), Some(mesh)) = (world_data.chunks.get(chunk_pos), meshes.get_mut(handle)) {
+            *mesh = build_chunk_mesh(
+                chunk,
+                *chunk_pos,
+                &atlas,
+                &world_data.generator.seed,
+                |tile| world_data.tile(tile),
+            );
         }
     }
+}
 
-    tiles
+fn load_chunk(
+    commands: &mut Commands<'_, '_>,
+    assets: &Res<'_, MyAssets>,
+    atlas: &TileAtlas,
+    sprite3d_params: &mut Sprite3dParams<'_, '_>,
+    creature_events: &mut EventWriter<SpawnCreature>,
+    world_data: &mut WorldData,
+    chunk_pos: ChunkPos,
+) {
+    let entities = spawn_chunk(commands, assets, atlas, sprite3d_params, world_data, chunk_pos);
+    // creatures only spawn the first time the chunk is loaded
+    for creature in world_data.take_creatures(chunk_pos) {
+        creature_events.send(SpawnCreature {
+            kind: creature.kind,
+            translation: chunk_pos.tile(creature.tile).center().with_height(1.),
+            health: None,
+        });
+    }
+    for creature in world_data.parked.remove(&chunk_pos).unwrap_or_default() {
+        creature_events.send(SpawnCreature {
+            kind: creature.kind,
+            translation: creature.translation,
+            health: Some(creature.health),
+        });
+    }
+    let Some(chunk) = world_data.chunks.get_mut(&chunk_pos) else {
+        return;
+    };
+    chunk.entities = entities;
+    chunk.set_is_loaded(true);
 }